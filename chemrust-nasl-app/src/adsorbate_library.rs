@@ -0,0 +1,185 @@
+use std::fmt::Display;
+
+use castep_periodic_table::element::ElementSymbol;
+use chemrust_nasl::Adsorbate;
+use nalgebra::Point3;
+
+use crate::element_data::covalent_radius;
+
+use ElementSymbol::{Ag, Au, Co, Cu, Fe, Ir, Ni, Pd, Pt, Rh, Ru, C, H, N, O};
+
+/// A common adsorbate with its geometry and usual bondlengths to the substrates.
+/// The anchor atom is at the origin and the molecule points along `+z`.
+#[derive(Debug)]
+pub struct AdsorbatePreset {
+    name: &'static str,
+    atoms: &'static [(ElementSymbol, [f64; 3])],
+    anchor: usize,
+    /// Typical anchor–substrate bondlengths (Å) on the elements listed
+    bondlengths: &'static [(ElementSymbol, f64)],
+}
+
+pub static ADSORBATE_PRESETS: [AdsorbatePreset; 7] = [
+    AdsorbatePreset {
+        name: "O",
+        atoms: &[(O, [0.0, 0.0, 0.0])],
+        anchor: 0,
+        bondlengths: &[
+            (Pt, 2.05),
+            (Pd, 2.05),
+            (Au, 2.15),
+            (Ag, 2.15),
+            (Cu, 1.95),
+            (Ni, 1.90),
+            (Co, 1.90),
+            (Ru, 2.05),
+            (Rh, 2.02),
+            (Ir, 2.05),
+        ],
+    },
+    AdsorbatePreset {
+        name: "OH",
+        atoms: &[(O, [0.0, 0.0, 0.0]), (H, [0.0, 0.0, 0.97])],
+        anchor: 0,
+        bondlengths: &[
+            (Pt, 2.05),
+            (Pd, 2.05),
+            (Au, 2.15),
+            (Ag, 2.15),
+            (Cu, 2.00),
+            (Ni, 1.95),
+            (Co, 1.95),
+            (Ru, 2.10),
+            (Rh, 2.05),
+            (Ir, 2.05),
+        ],
+    },
+    AdsorbatePreset {
+        name: "OOH",
+        atoms: &[
+            (O, [0.0, 0.0, 0.0]),
+            (O, [0.75, 0.0, 1.25]),
+            (H, [1.69, 0.0, 0.96]),
+        ],
+        anchor: 0,
+        bondlengths: &[
+            (Pt, 2.00),
+            (Pd, 2.05),
+            (Au, 2.15),
+            (Ag, 2.20),
+            (Cu, 1.95),
+            (Ni, 1.95),
+            (Co, 1.95),
+            (Ru, 2.05),
+            (Ir, 2.00),
+        ],
+    },
+    AdsorbatePreset {
+        name: "H",
+        atoms: &[(H, [0.0, 0.0, 0.0])],
+        anchor: 0,
+        bondlengths: &[
+            (Pt, 1.85),
+            (Pd, 1.80),
+            (Au, 1.80),
+            (Ag, 1.85),
+            (Cu, 1.75),
+            (Ni, 1.70),
+            (Co, 1.70),
+            (Ru, 1.85),
+            (Rh, 1.80),
+            (Ir, 1.85),
+        ],
+    },
+    AdsorbatePreset {
+        name: "CO",
+        atoms: &[(C, [0.0, 0.0, 0.0]), (O, [0.0, 0.0, 1.15])],
+        anchor: 0,
+        bondlengths: &[
+            (Pt, 1.85),
+            (Pd, 1.95),
+            (Au, 2.00),
+            (Cu, 1.90),
+            (Ni, 1.80),
+            (Co, 1.80),
+            (Ru, 1.90),
+            (Rh, 1.85),
+            (Ir, 1.85),
+        ],
+    },
+    AdsorbatePreset {
+        name: "N2",
+        atoms: &[(N, [0.0, 0.0, 0.0]), (N, [0.0, 0.0, 1.10])],
+        anchor: 0,
+        bondlengths: &[(Pt, 2.00), (Ni, 1.85), (Fe, 1.85), (Ru, 1.95)],
+    },
+    AdsorbatePreset {
+        name: "COOH",
+        atoms: &[
+            (C, [0.0, 0.0, 0.0]),
+            (O, [-1.05, 0.0, 0.60]),
+            (O, [1.17, 0.0, 0.68]),
+            (H, [0.92, 0.0, 1.62]),
+        ],
+        anchor: 0,
+        bondlengths: &[(Pt, 2.05), (Pd, 2.05), (Au, 2.05), (Ag, 2.15), (Cu, 1.95)],
+    },
+];
+
+/// Look up the preset by name, ignoring the case and a trailing `*`, e.g. `OOH*`.
+pub fn find_preset(name: &str) -> Option<&'static AdsorbatePreset> {
+    let name = name.trim().trim_end_matches('*');
+    ADSORBATE_PRESETS
+        .iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name))
+}
+
+impl AdsorbatePreset {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn anchor_symbol(&self) -> ElementSymbol {
+        self.atoms[self.anchor].0
+    }
+
+    /// The typical bondlength to the substrate element, or the sum of the
+    /// covalent radii of the anchor and the substrate element if not listed.
+    pub fn default_bondlength(&self, substrate: ElementSymbol) -> f64 {
+        self.bondlengths
+            .iter()
+            .find(|(symbol, _)| *symbol == substrate)
+            .map(|(_, length)| *length)
+            .unwrap_or_else(|| covalent_radius(self.anchor_symbol()) + covalent_radius(substrate))
+    }
+
+    pub fn to_adsorbate(&self) -> Adsorbate {
+        let (symbols, coords): (Vec<ElementSymbol>, Vec<Point3<f64>>) = self
+            .atoms
+            .iter()
+            .map(|(symbol, xyz)| (*symbol, Point3::from(*xyz)))
+            .unzip();
+        Adsorbate::new(symbols, &coords, self.anchor)
+    }
+}
+
+impl Display for AdsorbatePreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use castep_periodic_table::element::ElementSymbol;
+
+    use super::find_preset;
+
+    #[test]
+    fn lookup_presets() {
+        let ooh = find_preset("ooh*").expect("OOH is a preset");
+        assert_eq!(ooh.anchor_symbol(), ElementSymbol::O);
+        assert_eq!(ooh.default_bondlength(ElementSymbol::Pt), 2.00);
+        assert!(find_preset("NH4").is_none());
+    }
+}
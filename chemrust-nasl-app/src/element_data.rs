@@ -0,0 +1,129 @@
+use castep_periodic_table::{
+    data::ELEMENT_TABLE,
+    element::{ElementSymbol, LookupElement},
+};
+
+/// Fallback for the elements without a covalent radius in the periodic table.
+const DEFAULT_COVALENT_RADIUS: f64 = 1.50;
+
+/// Fraction of the summed covalent radii taken as the default exclusion radius.
+const EXCLUSION_SCALE: f64 = 0.75;
+
+/// Covalent radius (Å) of the element from `castep-periodic-table`.
+pub fn covalent_radius(symbol: ElementSymbol) -> f64 {
+    ELEMENT_TABLE
+        .get_by_symbol(symbol)
+        .covalent_radius()
+        .unwrap_or(DEFAULT_COVALENT_RADIUS)
+}
+
+/// Default minimum approach distance between the new atom and an atom of `symbol`
+pub fn default_exclusion_radius(new_element: ElementSymbol, symbol: ElementSymbol) -> f64 {
+    EXCLUSION_SCALE * (covalent_radius(new_element) + covalent_radius(symbol))
+}
+
+/// Elements of the d and f blocks, whose open shells call for spin polarisation.
+pub fn is_d_or_f_block(symbol: ElementSymbol) -> bool {
+    matches!(
+        ELEMENT_TABLE.get_by_symbol(symbol).atomic_number(),
+        21..=30 | 39..=48 | 57..=80 | 89..=112
+    )
+}
+
+/// Unpaired electrons of the free atoms of Sc - Zn, Y - Cd, and La with
+/// Hf - Hg, in their ground state configurations, e.g. 3 for Co (3d7 4s2).
+const D_BLOCK_SPINS: [[u8; 10]; 3] = [
+    [1, 2, 3, 6, 5, 4, 3, 2, 1, 0],
+    [1, 2, 5, 6, 5, 4, 3, 0, 1, 0],
+    [1, 2, 3, 4, 5, 4, 3, 2, 1, 0],
+];
+
+/// Unpaired electrons of the free atoms of La - Lu.
+const LANTHANIDE_SPINS: [u8; 15] = [1, 2, 3, 4, 5, 6, 7, 8, 5, 4, 3, 2, 1, 0, 1];
+
+/// Default initial spin (in μB, or unpaired electrons) of the new atoms of
+/// the 3d, 4d and 5d metals and the lanthanides, that of the free atom.
+/// `None` for the closed shells and the other elements.
+pub fn default_initial_spin(symbol: ElementSymbol) -> Option<f64> {
+    let spin = match ELEMENT_TABLE.get_by_symbol(symbol).atomic_number() as usize {
+        n @ 21..=30 => D_BLOCK_SPINS[0][n - 21],
+        n @ 39..=48 => D_BLOCK_SPINS[1][n - 39],
+        n @ 57..=71 => LANTHANIDE_SPINS[n - 57],
+        n @ 72..=80 => D_BLOCK_SPINS[2][n - 71],
+        _ => 0,
+    };
+    (spin > 0).then_some(spin as f64)
+}
+
+/// Principal and angular quantum numbers of the open d or f shell, e.g.
+/// `(3, 'd')` for Fe or `(4, 'f')` for Ce, on which the Hubbard U acts.
+pub fn hubbard_manifold(symbol: ElementSymbol) -> Option<(u32, char)> {
+    match ELEMENT_TABLE.get_by_symbol(symbol).atomic_number() {
+        21..=30 => Some((3, 'd')),
+        39..=48 => Some((4, 'd')),
+        57..=71 => Some((4, 'f')),
+        72..=80 => Some((5, 'd')),
+        89..=103 => Some((5, 'f')),
+        104..=112 => Some((6, 'd')),
+        _ => None,
+    }
+}
+
+/// Default Hubbard U (eV): the values of the Materials Project for the oxides
+/// of V, Cr, Mn, Fe, Co, Ni, Mo and W, and common values for the 4f states of
+/// the lanthanides. `None` for the other elements.
+pub fn default_hubbard_u(symbol: ElementSymbol) -> Option<f64> {
+    match ELEMENT_TABLE.get_by_symbol(symbol).atomic_number() {
+        23 => Some(3.25),
+        24 => Some(3.7),
+        25 => Some(3.9),
+        26 => Some(5.3),
+        27 => Some(3.32),
+        28 => Some(6.2),
+        42 => Some(4.38),
+        74 => Some(6.2),
+        58 => Some(5.0),
+        59..=71 => Some(6.0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use castep_periodic_table::element::ElementSymbol;
+
+    use super::{covalent_radius, default_exclusion_radius, default_initial_spin};
+
+    #[test]
+    fn covalent_radii_lookup() {
+        assert_eq!(covalent_radius(ElementSymbol::H), 0.32);
+        assert_eq!(covalent_radius(ElementSymbol::Pt), 1.23);
+        // Not in the periodic table, takes the fallback
+        assert_eq!(covalent_radius(ElementSymbol::Fr), 1.50);
+    }
+
+    #[test]
+    fn exclusion_radii() {
+        // 0.75 * (0.63 + 1.23)
+        let radius = default_exclusion_radius(ElementSymbol::O, ElementSymbol::Pt);
+        assert!((radius - 1.395).abs() < 1e-9);
+        assert_eq!(
+            radius,
+            default_exclusion_radius(ElementSymbol::Pt, ElementSymbol::O)
+        );
+        // A nearby H blocks less space than a metal atom
+        assert!(
+            default_exclusion_radius(ElementSymbol::O, ElementSymbol::H)
+                < default_exclusion_radius(ElementSymbol::O, ElementSymbol::Ni)
+        );
+    }
+
+    #[test]
+    fn initial_spins_lookup() {
+        assert_eq!(default_initial_spin(ElementSymbol::Fe), Some(4.0));
+        assert_eq!(default_initial_spin(ElementSymbol::Pt), Some(2.0));
+        assert_eq!(default_initial_spin(ElementSymbol::Gd), Some(8.0));
+        assert_eq!(default_initial_spin(ElementSymbol::Pd), None);
+        assert_eq!(default_initial_spin(ElementSymbol::O), None);
+    }
+}
@@ -4,7 +4,7 @@ use std::fmt::Display;
 pub enum RunError {
     Message(String),
     FormatError(FormatError),
-    IO,
+    IO(std::io::Error),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -13,6 +13,10 @@ pub enum FormatError {
     Supported,
     Compatible,
     ReadToString,
+    /// A loop with the values stopping in the middle of a row
+    IncompleteLoop,
+    /// A space group other than P1 without the symmetry operations
+    MissingSymmetryOps,
 }
 
 impl Display for FormatError {
@@ -27,6 +31,12 @@ impl Display for FormatError {
                 f.write_str("The file does not have extension suffix `.xxx`")
             }
             FormatError::ReadToString => f.write_str("Failed to read to string"),
+            FormatError::IncompleteLoop => {
+                f.write_str("A loop in the file has an incomplete row of values")
+            }
+            FormatError::MissingSymmetryOps => f.write_str(
+                "The symmetry operations are not listed, and the space group is not given as P1",
+            ),
         }
     }
 }
@@ -36,10 +46,18 @@ impl Display for RunError {
         match self {
             RunError::Message(m) => f.write_str(m),
             RunError::FormatError(m) => write!(f, "{m}"),
-            RunError::IO => f.write_str("Error in IO operations"),
+            RunError::IO(e) => write!(f, "Error in IO operations: {e}"),
         }
     }
 }
 
-impl std::error::Error for RunError {}
+impl std::error::Error for RunError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunError::FormatError(e) => Some(e),
+            RunError::IO(e) => Some(e),
+            RunError::Message(_) => None,
+        }
+    }
+}
 impl std::error::Error for FormatError {}
@@ -0,0 +1,334 @@
+use std::path::PathBuf;
+
+use castep_cell_io::{CastepTask, CellDocument, SeedfileGenerator};
+use castep_periodic_table::{
+    data::ELEMENT_TABLE,
+    element::{ElementSymbol, LookupElement},
+};
+use chemrust_core::data::{atom::CoreAtomData, lattice::CrystalModel};
+
+use crate::{
+    element_data::{hubbard_manifold, is_d_or_f_block},
+    yaml_parser::TaskTable,
+};
+
+use super::{
+    cell_editor::{block_rows, cell_block, find_block, rendered_entries, set_entries},
+    potentials::find_potential,
+};
+
+/// The `.cell` of the seed, with the k-points of the model replaced by the
+/// `KPOINTS_MP_GRID`, and the species blocks completed for the new atoms
+/// after the `num_base_atoms` of the base model. The `SPECIES_POT` names the
+/// potentials found in the `potential_files`. The atoms of the `spins`, by
+/// index, take their initial `SPIN`, and the other new atoms none. The
+/// `IONIC_CONSTRAINTS` and `HUBBARD_U`, which `castep-cell-io` does not
+/// model, are added when any atom is `fixed` or any element has a U.
+pub fn seed_cell_content(
+    model: &CellDocument,
+    num_base_atoms: usize,
+    grid: [u32; 3],
+    fixed: &[usize],
+    spins: &[(usize, f64)],
+    hubbard_u: &[(ElementSymbol, f64)],
+    potential_files: &[PathBuf],
+) -> String {
+    let mut cell = model.clone();
+    let kpoints = format!("KPOINTS_MP_GRID : {} {} {}\n", grid[0], grid[1], grid[2]);
+    set_entries(&mut cell, &kpoints).expect("The `KPOINTS_MP_GRID` is valid");
+    complete_species_blocks(&mut cell);
+    set_species_pot(&mut cell, potential_files);
+    let mut content = [
+        cell.get_cell_parameters().to_string(),
+        positions_block(&cell, num_base_atoms, spins),
+        rendered_entries(&cell).join("\n"),
+    ]
+    .concat();
+    if !hubbard_u.is_empty() {
+        content.push_str(&cell_block("HUBBARD_U", &hubbard_u_lines(hubbard_u)));
+    }
+    if !fixed.is_empty() {
+        content.push_str(&cell_block(
+            "IONIC_CONSTRAINTS",
+            &ionic_constraints(model, fixed),
+        ));
+    }
+    content
+}
+
+/// The positions block of the `cell`, with the `SPIN=` of the atoms in the
+/// `spins` in place of the spin of the free atom written by `castep-cell-io`.
+/// The new atoms after the `num_base_atoms` take no spin unless given one.
+fn positions_block(cell: &CellDocument, num_base_atoms: usize, spins: &[(usize, f64)]) -> String {
+    let block = cell.get_atom_data();
+    let rows: Vec<String> = block
+        .positions()
+        .iter()
+        .enumerate()
+        .map(
+            |(i, position)| match spins.iter().find(|&&(atom, _)| atom == i) {
+                Some(&(_, spin)) => format!("{} SPIN={:14.10}", position.export(false), spin),
+                None if i >= num_base_atoms => position.export(false),
+                None => position.export(block.spin_polarised()),
+            },
+        )
+        .collect();
+    cell_block(&format!("{:?}", block.keyword()), &rows)
+}
+
+/// Three constraints per fixed atom, along `x, y, z`, with the atoms numbered
+/// within their species.
+fn ionic_constraints(model: &CellDocument, fixed: &[usize]) -> Vec<String> {
+    let symbols = model.get_atom_data().symbols_repr();
+    let mut lines: Vec<String> = Vec::new();
+    fixed.iter().filter(|&&i| i < symbols.len()).for_each(|&i| {
+        let symbol = symbols[i];
+        let index_in_species = symbols[..=i].iter().filter(|&&s| s == symbol).count();
+        let name = ELEMENT_TABLE.get_by_symbol(symbol).symbol_to_string();
+        ["1.0 0.0 0.0", "0.0 1.0 0.0", "0.0 0.0 1.0"]
+            .iter()
+            .for_each(|direction| {
+                lines.push(format!(
+                    "{:>8}{:>8}{:>8}  {}",
+                    lines.len() + 1,
+                    name,
+                    index_in_species,
+                    direction
+                ));
+            });
+    });
+    lines
+}
+
+/// The U on the open shell of each element, e.g. `Fe d: 5.3`, in eV.
+fn hubbard_u_lines(hubbard_u: &[(ElementSymbol, f64)]) -> Vec<String> {
+    let mut lines = vec!["eV".to_string()];
+    hubbard_u.iter().for_each(|&(symbol, u)| {
+        if let Some((_, shell)) = hubbard_manifold(symbol) {
+            let name = ELEMENT_TABLE.get_by_symbol(symbol).symbol_to_string();
+            lines.push(format!("{:>8}  {}: {:.4}", name, shell, u));
+        }
+    });
+    lines
+}
+
+/// Whether the `row` of a species block is of the element `name`.
+fn is_row_of(row: &str, name: &str) -> bool {
+    row.split_whitespace()
+        .next()
+        .is_some_and(|label| label.eq_ignore_ascii_case(name))
+}
+
+/// Add the entries of the elements missing from the `SPECIES_MASS`,
+/// `SPECIES_POT` and `SPECIES_LCAO_STATES` of the `cell`, e.g. of the new
+/// element, keeping the entries of the base model. The entries come from the
+/// blocks `castep-cell-io` generates by the periodic table. The blocks the
+/// model does not have are left out.
+fn complete_species_blocks(cell: &mut CellDocument) {
+    let generated = rendered_entries(
+        &SeedfileGenerator::new(CastepTask::GeometryOptimization, cell.clone())
+            .generate_cell_file(),
+    );
+    let entries = rendered_entries(cell);
+    let content: String = ["SPECIES_MASS", "SPECIES_POT", "SPECIES_LCAO_STATES"]
+        .iter()
+        .filter_map(|&name| {
+            let mut rows = block_rows(find_block(&entries, name)?);
+            block_rows(find_block(&generated, name)?)
+                .into_iter()
+                .for_each(|row| {
+                    let label = row.split_whitespace().next().unwrap_or_default();
+                    if !rows.iter().any(|listed| is_row_of(listed, label)) {
+                        rows.push(row);
+                    }
+                });
+            Some(cell_block(name, &rows))
+        })
+        .collect();
+    set_entries(cell, &content).expect("The species blocks are written by `castep-cell-io`");
+}
+
+/// Name the potentials found in the `potential_files` in the `SPECIES_POT`
+/// of the `cell`, the block added when the model has none.
+pub fn set_species_pot(cell: &mut CellDocument, potential_files: &[PathBuf]) {
+    let found: Vec<(String, String)> = cell
+        .get_elements()
+        .iter()
+        .filter_map(|&symbol| {
+            let name = ELEMENT_TABLE.get_by_symbol(symbol).symbol_to_string();
+            let filename = find_potential(potential_files, &name)?.file_name()?;
+            Some((name, filename.to_string_lossy().to_string()))
+        })
+        .collect();
+    if found.is_empty() {
+        return;
+    }
+    let entries = rendered_entries(cell);
+    let mut rows = find_block(&entries, "SPECIES_POT")
+        .map(|block| block_rows(block))
+        .unwrap_or_default();
+    rows.retain(|row| found.iter().all(|(name, _)| !is_row_of(row, name)));
+    rows.extend(
+        found
+            .iter()
+            .map(|(name, filename)| format!("{:>8}  {}", name, filename)),
+    );
+    set_entries(cell, &cell_block("SPECIES_POT", &rows)).expect("The `SPECIES_POT` is valid");
+}
+
+/// Spin polarised when any element of the model is of the d or f blocks.
+pub fn is_spin_polarized(model: &CellDocument) -> bool {
+    model
+        .get_atom_data()
+        .symbols_repr()
+        .iter()
+        .any(|&symbol| is_d_or_f_block(symbol))
+}
+
+/// The `.param` of a geometry optimisation, with `metals_method` of `edft` or
+/// `dm`. The `cut_off_energy` is written when set in the task, otherwise
+/// CASTEP takes it from its `basis_precision`. The other parameters are left
+/// to the defaults of CASTEP.
+pub fn castep_param(task_config: &TaskTable, spin_polarized: bool) -> String {
+    let metals_method = if task_config.edft() { "edft" } else { "dm" };
+    let mut lines = vec![
+        "task : GeometryOptimization".to_string(),
+        "comment : CASTEP calculation generated by rhino".to_string(),
+        "xc_functional : PBE".to_string(),
+        format!("spin_polarized : {spin_polarized}"),
+    ];
+    if let Some(cut_off_energy) = task_config.cut_off_energy() {
+        lines.push(format!("cut_off_energy : {cut_off_energy:.1}"));
+    }
+    lines.push("fix_occupancy : false".to_string());
+    lines.push(format!("metals_method : {metals_method}"));
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod test {
+    use castep_cell_io::IonicPosition;
+    use castep_periodic_table::element::ElementSymbol;
+    use chemrust_core::data::lattice::CrystalModel;
+
+    use crate::execution::{
+        cell_editor::{block_rows, find_block, rendered_entries},
+        format_loader::load_cell_content,
+        test_fixtures::{task, PT_CO},
+    };
+
+    use super::{castep_param, seed_cell_content};
+
+    #[test]
+    fn geometry_optimisation_param() {
+        let mut task_config = task(true);
+        let param = castep_param(&task_config, true);
+        assert!(param.contains("spin_polarized : true\n"));
+        assert!(param.contains("metals_method : edft\n"));
+        assert!(!param.contains("cut_off_energy"));
+        assert!(!param.contains("spin_fix"));
+        task_config.cut_off_energy = Some(450.0);
+        task_config.edft = false;
+        let param = castep_param(&task_config, false);
+        assert!(param.contains("cut_off_energy : 450.0\n"));
+        assert!(param.contains("metals_method : dm\n"));
+    }
+
+    #[test]
+    fn hubbard_u_block() {
+        let model = load_cell_content(PT_CO.to_string()).unwrap();
+        let content = seed_cell_content(&model, 4, [4, 4, 1], &[], &[], &[], &[]);
+        assert!(!content.contains("HUBBARD_U"));
+        let content = seed_cell_content(
+            &model,
+            4,
+            [4, 4, 1],
+            &[],
+            &[],
+            &[(ElementSymbol::Pt, 2.5), (ElementSymbol::O, 1.0)],
+            &[],
+        );
+        // O has no open d or f shell
+        assert!(
+            content.ends_with("%BLOCK HUBBARD_U\neV\n      Pt  d: 2.5000\n%ENDBLOCK HUBBARD_U\n\n")
+        );
+    }
+
+    #[test]
+    fn seed_kpoints_and_spins() {
+        let mut model = load_cell_content(format!(
+            "{PT_CO}\n%BLOCK KPOINTS_LIST\n0.0 0.0 0.0 1.0\n%ENDBLOCK KPOINTS_LIST\n"
+        ))
+        .unwrap();
+        [[0.25, 0.5, 0.2], [0.75, 0.5, 0.2]]
+            .iter()
+            .for_each(|&frac_coord| {
+                model
+                    .get_atom_data_mut()
+                    .positions_mut()
+                    .push(IonicPosition::new(ElementSymbol::Pt, frac_coord, None));
+            });
+        let content = seed_cell_content(&model, 4, [4, 4, 1], &[], &[(5, 1.5)], &[], &[]);
+        // `castep-cell-io` reads the `KPOINTS_MP_GRID` and writes it back
+        // under the synonym `KPOINT_MP_GRID`
+        assert!(content.contains("KPOINT_MP_GRID : 4 4 1\n"));
+        assert!(!content.contains("KPOINTS_MP_GRID"));
+        assert!(!content.contains("KPOINTS_LIST"));
+        let pt_rows: Vec<&str> = content
+            .lines()
+            .filter(|line| line.trim_start().starts_with("Pt "))
+            .collect();
+        assert_eq!(pt_rows.len(), 4);
+        // The Pt of the model keep the spin of the free atom
+        assert!(pt_rows[..2].iter().all(|row| row.contains("SPIN=")));
+        // The new Pt without an initial spin is unpolarised
+        assert!(!pt_rows[2].contains("SPIN="));
+        // The new Pt with an initial spin takes its own
+        assert!(pt_rows[3].ends_with("SPIN=  1.5000000000"));
+        assert_eq!(pt_rows[3].matches("SPIN=").count(), 1);
+        // The seed is read back by `castep-cell-io`
+        assert!(load_cell_content(content).is_ok());
+    }
+
+    /// Rows of the species block `name` in the seed, read back by `castep-cell-io`.
+    fn species_rows(content: &str, name: &str) -> Option<Vec<String>> {
+        let seed = load_cell_content(content.to_string()).unwrap();
+        find_block(&rendered_entries(&seed), name).map(|block| block_rows(block))
+    }
+
+    #[test]
+    fn complete_existing_species_blocks() {
+        let model = load_cell_content(format!(
+            "{PT_CO}\n%BLOCK SPECIES_MASS\nPt 195.0\nC 12.0\n%ENDBLOCK SPECIES_MASS\n"
+        ))
+        .unwrap();
+        let mut new_model = model.clone();
+        new_model
+            .get_atom_data_mut()
+            .positions_mut()
+            .push(IonicPosition::new(ElementSymbol::N, [0.5, 0.5, 0.3], None));
+        let content = seed_cell_content(&new_model, 4, [1, 1, 1], &[], &[], &[], &[]);
+        let rows = species_rows(&content, "SPECIES_MASS").unwrap();
+        let masses: Vec<(&str, f64)> = rows
+            .iter()
+            .map(|row| {
+                let mut tokens = row.split_whitespace();
+                let label = tokens.next().unwrap();
+                (label, tokens.next().unwrap().parse().unwrap())
+            })
+            .collect();
+        // The masses of the model are kept, and the missing O and N are added
+        assert_eq!(masses.len(), 4);
+        assert_eq!(masses[0], ("Pt", 195.0));
+        assert_eq!(masses[1], ("C", 12.0));
+        assert!(masses
+            .iter()
+            .any(|&(label, mass)| label == "N" && (mass - 14.007).abs() < 0.01));
+        // The blocks the model does not have are not added
+        assert!(species_rows(&content, "SPECIES_POT").is_none());
+        assert!(species_rows(&content, "SPECIES_LCAO_STATES").is_none());
+        let bare = load_cell_content(PT_CO.to_string()).unwrap();
+        let content = seed_cell_content(&bare, 4, [1, 1, 1], &[], &[], &[], &[]);
+        assert!(!content.contains("SPECIES_"));
+    }
+}
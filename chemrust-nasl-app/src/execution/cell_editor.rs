@@ -0,0 +1,111 @@
+use std::mem::discriminant;
+
+use castep_cell_io::{CellDocument, CellParseError};
+
+use super::format_loader::entries_document;
+
+/// Set the entries of the `.cell` blocks and keywords in the `content` on
+/// the `cell`, in place of its entries of the same kind, e.g. a
+/// `KPOINTS_MP_GRID` in place of its `KPOINTS_LIST`.
+pub fn set_entries(cell: &mut CellDocument, content: &str) -> Result<(), CellParseError> {
+    let new_entries = entries_document(content)?
+        .other_entries()
+        .cloned()
+        .unwrap_or_default();
+    let mut entries = cell.other_entries().cloned().unwrap_or_default();
+    entries.retain(|entry| {
+        new_entries
+            .iter()
+            .all(|new_entry| discriminant(new_entry) != discriminant(entry))
+    });
+    entries.extend(new_entries);
+    cell.set_entries(Some(entries));
+    Ok(())
+}
+
+/// The entries of the `cell` as they are written out, e.g. to read the rows
+/// of its `SPECIES_POT`.
+pub fn rendered_entries(cell: &CellDocument) -> Vec<String> {
+    cell.other_entries()
+        .map(|entries| entries.iter().map(|entry| entry.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// The rendered entry of the block `name`, case-insensitive as in CASTEP.
+pub fn find_block<'a>(entries: &'a [String], name: &str) -> Option<&'a String> {
+    entries.iter().find(|entry| {
+        let mut tokens = entry.split_whitespace();
+        tokens
+            .next()
+            .is_some_and(|mark| mark.eq_ignore_ascii_case("%BLOCK"))
+            && tokens
+                .next()
+                .is_some_and(|tag| tag.eq_ignore_ascii_case(name))
+    })
+}
+
+/// Rows of a block, without the block marks and the blank lines.
+pub fn block_rows(block: &str) -> Vec<String> {
+    block
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('%'))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// A block laid out as `castep-cell-io` writes its blocks, for those it does
+/// not model, e.g. `HUBBARD_U`.
+pub fn cell_block(name: &str, rows: &[String]) -> String {
+    format!("%BLOCK {name}\n{}\n%ENDBLOCK {name}\n\n", rows.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use chemrust_core::data::{atom::CoreAtomData, lattice::CrystalModel};
+
+    use crate::execution::format_loader::load_cell_content;
+
+    use super::{block_rows, cell_block, find_block, rendered_entries, set_entries};
+
+    #[test]
+    fn replace_entries_of_a_kind() {
+        let mut cell = load_cell_content(
+            "%BLOCK LATTICE_CART
+3.0 0.0 0.0
+0.0 3.0 0.0
+0.0 0.0 10.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+Pt 0.0 0.0 0.0
+%ENDBLOCK POSITIONS_FRAC
+
+%BLOCK KPOINTS_LIST
+0.0 0.0 0.0 1.0
+%ENDBLOCK KPOINTS_LIST
+
+%BLOCK SPECIES_MASS
+Pt 195.08
+%ENDBLOCK SPECIES_MASS
+"
+            .to_string(),
+        )
+        .unwrap();
+        set_entries(&mut cell, "KPOINTS_MP_GRID : 3 3 1\n").unwrap();
+        let entries = rendered_entries(&cell);
+        assert_eq!(entries.len(), 2);
+        assert!(find_block(&entries, "kpoints_list").is_none());
+        // Written back under the synonym `KPOINT_MP_GRID` by `castep-cell-io`
+        assert!(entries
+            .iter()
+            .any(|entry| entry.starts_with("KPOINT_MP_GRID : 3 3 1")));
+        let mass = find_block(&entries, "SPECIES_MASS").unwrap();
+        assert_eq!(block_rows(mass).len(), 1);
+        // The model is kept
+        assert_eq!(cell.get_atom_data().symbols_repr().len(), 1);
+        assert_eq!(
+            cell_block("HUBBARD_U", &["eV".to_string()]),
+            "%BLOCK HUBBARD_U\neV\n%ENDBLOCK HUBBARD_U\n\n"
+        );
+    }
+}
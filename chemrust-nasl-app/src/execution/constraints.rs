@@ -0,0 +1,123 @@
+use chemrust_core::data::lattice::{CrystalModel, UnitCellParameters};
+use chemrust_nasl::SlabAnalysis;
+use nalgebra::Point3;
+
+use crate::{
+    supportive_data::{FixAtoms, FractionalCoordRange},
+    yaml_parser::TaskTable,
+};
+
+use super::helpers::{get_to_check_atom, MIN_VACUUM_GAP};
+
+/// Atoms closer than this (Å) along the surface normal are in the same layer.
+const LAYER_TOLERANCE: f64 = 0.5;
+
+/// Atoms of the model fixed in the geometry optimisations of the seeds.
+#[derive(Debug, Clone, Default)]
+pub struct FixedAtoms {
+    /// Fixed by the height or the layers, whatever the site
+    fixed: Vec<bool>,
+    except_site: bool,
+}
+
+impl FixedAtoms {
+    pub fn new<T: CrystalModel>(model: &T, task_config: &TaskTable) -> Self {
+        let all_range = FractionalCoordRange::new(0.0, 1.0);
+        let points: Vec<Point3<f64>> = get_to_check_atom(model, all_range, all_range, all_range)
+            .iter()
+            .map(|(_i, point)| *point)
+            .collect();
+        let Some(fix_atoms) = task_config.fix_atoms() else {
+            return Self {
+                fixed: vec![false; points.len()],
+                except_site: false,
+            };
+        };
+        let lattice_bases = model.get_cell_parameters().lattice_bases();
+        let slab = SlabAnalysis::new(&points, lattice_bases, MIN_VACUUM_GAP);
+        let axis = slab.map(|slab| slab.vacuum_axis()).unwrap_or(2);
+        let inverse_bases = lattice_bases
+            .try_inverse()
+            .expect("Matrix is not invertible");
+        // Heights along the surface normal, or the lattice vector `c` of bulk models.
+        let heights: Vec<f64> = points
+            .iter()
+            .map(|p| match slab {
+                Some(slab) => {
+                    let height = slab.height_above_top(p);
+                    // Atoms wrapped across the cell boundary belong under the bottom.
+                    if height > LAYER_TOLERANCE {
+                        height - lattice_bases.column(axis).dot(&slab.normal().into_inner())
+                    } else {
+                        height
+                    }
+                }
+                None => (inverse_bases * p)[axis] * lattice_bases.column(axis).norm(),
+            })
+            .collect();
+        let mut fixed = vec![false; points.len()];
+        if let Some(below) = fix_atoms.below() {
+            points.iter().enumerate().for_each(|(i, p)| {
+                if (inverse_bases * p)[axis] < below {
+                    fixed[i] = true;
+                }
+            });
+        }
+        if let Some(num_layers) = fix_atoms.bottom_layers() {
+            layer_indices(&heights)
+                .iter()
+                .enumerate()
+                .for_each(|(i, &layer)| {
+                    if layer < num_layers {
+                        fixed[i] = true;
+                    }
+                });
+        }
+        Self {
+            fixed,
+            except_site: fix_atoms.except_site(),
+        }
+    }
+
+    /// Number of the atoms of the model fixed whatever the site.
+    pub fn num_fixed(&self) -> usize {
+        self.fixed.iter().filter(|&&f| f).count()
+    }
+
+    /// Indices of the fixed atoms of the model, for the seed at the site
+    /// coordinated by `site_atoms`. The new atoms are never fixed.
+    pub fn for_site(&self, site_atoms: &[usize]) -> Vec<usize> {
+        (0..self.fixed.len())
+            .filter(|i| self.fixed[*i] || (self.except_site && !site_atoms.contains(i)))
+            .collect()
+    }
+}
+
+/// Layer of each atom counting from the bottom, split where the heights of
+/// the sorted atoms jump by more than the tolerance.
+fn layer_indices(heights: &[f64]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..heights.len()).collect();
+    order.sort_by(|&a, &b| heights[a].total_cmp(&heights[b]));
+    let mut layers = vec![0_usize; heights.len()];
+    order.windows(2).fold(0_usize, |layer, pair| {
+        let layer = if heights[pair[1]] - heights[pair[0]] > LAYER_TOLERANCE {
+            layer + 1
+        } else {
+            layer
+        };
+        layers[pair[1]] = layer;
+        layer
+    });
+    layers
+}
+
+#[cfg(test)]
+mod test {
+    use super::layer_indices;
+
+    #[test]
+    fn layers_by_height() {
+        let heights = [-4.6, -2.3, -4.5, 0.0, -2.2, -0.1];
+        assert_eq!(layer_indices(&heights), vec![0, 1, 0, 2, 1, 2]);
+    }
+}
@@ -1,18 +1,124 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
-use std::fs::{create_dir_all, write};
+use std::io::Error as IoError;
 use std::ops::ControlFlow;
 use std::path::{Path, PathBuf};
-use std::{fs::create_dir, io::Error as IoError};
 
 use castep_cell_io::{CellDocument, IonicPosition};
-use chemrust_core::data::lattice::UnitCellParameters;
-use chemrust_nasl::{CoordSite, DelegatePoint, MultiCoordPoint, SearchReports, Visualize};
+use castep_periodic_table::element::ElementSymbol;
+use chemrust_core::data::{
+    atom::CoreAtomData,
+    lattice::{CrystalModel, UnitCellParameters},
+};
+use chemrust_nasl::{
+    Adsorbate, CoordSite, DelegatePoint, MultiCoordPoint, Periodicity, SearchConfig, SearchReports,
+    SiteIndex, Visualize,
+};
 use crystal_cif_io::to_cif_document;
+use nalgebra::Point3;
+use serde::Serialize;
 
-use crate::yaml_parser::TaskTable;
+use crate::{
+    element_data::default_exclusion_radius,
+    supportive_data::{ExportFormat, ExportLayout},
+    yaml_parser::TaskTable,
+};
+
+use super::{
+    castep_inputs::{castep_param, is_spin_polarized, seed_cell_content},
+    constraints::FixedAtoms,
+    extxyz::extxyz_frame,
+    format_loader::Poscar,
+    job_scripts::JobScripts,
+    manifest::ExportManifest,
+    qe_inputs::pw_input,
+    vasp_inputs::{incar, kpoints, ldau, potcar_spec},
+};
+
+/// The model the new atoms are added to, and the format of the seeds.
+pub struct BaseModel {
+    cell: CellDocument,
+    format: ExportFormat,
+    /// Written out for the `vasp` seeds, keeping the selective dynamics when
+    /// the model is read from a `POSCAR`.
+    poscar: Option<Poscar>,
+    kpoint_grid: [u32; 3],
+    fixed_atoms: FixedAtoms,
+    /// Every file is written through it, to be listed for the dry or clean runs.
+    manifest: ExportManifest,
+    job_scripts: Option<JobScripts>,
+    /// Potentials of the `potential_dir`, named by the `SPECIES_POT` of the
+    /// `.cell` seeds
+    potential_files: Vec<PathBuf>,
+    /// Seeds of the array job, relative to the `export_dir`
+    seeds: RefCell<Vec<String>>,
+}
+
+impl BaseModel {
+    pub fn new(
+        cell: CellDocument,
+        format: ExportFormat,
+        poscar: Option<Poscar>,
+        kpoint_grid: [u32; 3],
+        fixed_atoms: FixedAtoms,
+        manifest: ExportManifest,
+        job_scripts: Option<JobScripts>,
+    ) -> Self {
+        let poscar = match format {
+            ExportFormat::Vasp => {
+                Some(poscar.unwrap_or_else(|| Poscar::from_model(&cell, "Generated by rhino")))
+            }
+            ExportFormat::Castep | ExportFormat::QuantumEspresso => poscar,
+        };
+        Self {
+            cell,
+            format,
+            poscar,
+            kpoint_grid,
+            fixed_atoms,
+            manifest,
+            job_scripts,
+            potential_files: Vec::new(),
+            seeds: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn with_potential_files(self, potential_files: Vec<PathBuf>) -> Self {
+        Self {
+            potential_files,
+            ..self
+        }
+    }
+
+    pub fn cell(&self) -> &CellDocument {
+        &self.cell
+    }
+
+    /// Monkhorst-Pack grid of the seeds, the same for all as the lattice is kept.
+    pub fn kpoint_grid(&self) -> [u32; 3] {
+        self.kpoint_grid
+    }
+
+    pub fn fixed_atoms(&self) -> &FixedAtoms {
+        &self.fixed_atoms
+    }
+
+    pub fn manifest(&self) -> &ExportManifest {
+        &self.manifest
+    }
+
+    pub fn job_scripts(&self) -> Option<&JobScripts> {
+        self.job_scripts.as_ref()
+    }
+
+    pub fn potential_files(&self) -> &[PathBuf] {
+        &self.potential_files
+    }
+}
 
 pub fn export_all<T: UnitCellParameters>(
-    base_model: &CellDocument,
+    base_model: &BaseModel,
     cell_param: &T,
     task_config: &TaskTable,
     results: &SearchReports,
@@ -20,58 +126,121 @@ pub fn export_all<T: UnitCellParameters>(
     let mut num_multi = 0_usize;
     let mut num_single = 0_usize;
     let mut num_double = 0_usize;
+    let mut frames: Vec<String> = Vec::new();
     if let Some(multi_points) = results.points() {
         let boundary_checked: Vec<MultiCoordPoint> =
-            points_boundary_check(multi_points, cell_param);
+            points_boundary_check(multi_points, cell_param, task_config.periodicity());
+        if task_config.extxyz() {
+            frames.extend(extxyz_frames(base_model, task_config, &boundary_checked));
+        }
         if boundary_checked.len() > 1 {
             export(base_model, cell_param, task_config, &boundary_checked)?;
             collectively_export(base_model, cell_param, task_config, &boundary_checked)?;
             num_multi = boundary_checked.len();
             println!(
-                "Exported {} multi-coordinated positions;",
-                boundary_checked.len()
+                "Exported {} multi-coordinated positions ({});",
+                boundary_checked.len(),
+                site_kind_summary(&boundary_checked)
             );
         }
     }
     if let Some(single_points) = results.viable_single_points() {
         let boundary_checked: Vec<DelegatePoint<1>> =
-            points_boundary_check(single_points, cell_param);
+            points_boundary_check(single_points, cell_param, task_config.periodicity());
+        if task_config.extxyz() {
+            frames.extend(extxyz_frames(base_model, task_config, &boundary_checked));
+        }
         if boundary_checked.len() > 1 {
             export(base_model, cell_param, task_config, &boundary_checked)?;
             collectively_export(base_model, cell_param, task_config, &boundary_checked)?;
             num_single = boundary_checked.len();
             println!(
-                "Exported {} possible singly-coordinated positions;",
-                boundary_checked.len()
+                "Exported {} possible singly-coordinated positions ({});",
+                boundary_checked.len(),
+                site_kind_summary(&boundary_checked)
             )
         }
     }
     if let Some(double_points) = results.viable_double_points() {
         let boundary_checked: Vec<DelegatePoint<2>> =
-            points_boundary_check(double_points, cell_param);
+            points_boundary_check(double_points, cell_param, task_config.periodicity());
+        if task_config.extxyz() {
+            frames.extend(extxyz_frames(base_model, task_config, &boundary_checked));
+        }
         if boundary_checked.len() > 1 {
             export(base_model, cell_param, task_config, &boundary_checked)?;
             collectively_export(base_model, cell_param, task_config, &boundary_checked)?;
             num_double = boundary_checked.len();
             println!(
-                "Exported {} possible doubly-coordinated positions;",
-                boundary_checked.len()
+                "Exported {} possible doubly-coordinated positions ({});",
+                boundary_checked.len(),
+                site_kind_summary(&boundary_checked)
             );
         }
     }
+    write_extxyz(base_model, task_config, &frames)?;
     Ok((num_multi, num_single, num_double))
 }
 
+fn extxyz_frames<T: CoordSite + Visualize>(
+    base_model: &BaseModel,
+    task_config: &TaskTable,
+    coord_sites: &[T],
+) -> Vec<String> {
+    coord_sites
+        .iter()
+        .map(|site| {
+            extxyz_frame(
+                base_model.cell(),
+                site,
+                &[(task_config.new_element().symbol(), site.determine_coord())],
+                task_config.periodicity(),
+            )
+        })
+        .collect()
+}
+
+/// All the frames in one file, when any.
+fn write_extxyz(
+    base_model: &BaseModel,
+    task_config: &TaskTable,
+    frames: &[String],
+) -> Result<(), IoError> {
+    if frames.is_empty() {
+        return Ok(());
+    }
+    let filename = extxyz_filename(task_config);
+    base_model.manifest().write(&filename, frames.concat())?;
+    println!(
+        "Wrote {} structures to {}",
+        frames.len(),
+        filename.display()
+    );
+    Ok(())
+}
+
+/// All the structures in one file, `<model>_candidates.extxyz`.
+fn extxyz_filename(task_config: &TaskTable) -> PathBuf {
+    let model_name = Path::new(task_config.model_path())
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .expect("Invalid filename");
+    Path::new(task_config.export_dir()).join(format!("{}_candidates.extxyz", model_name))
+}
+
+/// Sites have been wrapped into the cell along the periodic directions,
+/// so only the non-periodic directions are checked.
 fn points_boundary_check<T: Visualize + Clone, U: UnitCellParameters>(
     points: &[T],
     cell_param: &U,
+    periodicity: Periodicity,
 ) -> Vec<T> {
     points
         .iter()
         .filter(|cp| {
             let frac_coord = cp.fractional_coord(cell_param.lattice_bases());
-            let check = frac_coord.iter().try_for_each(|&v| {
-                if !(0.0..=1.0).contains(&v) {
+            let check = frac_coord.iter().enumerate().try_for_each(|(axis, &v)| {
+                if !periodicity.is_periodic(axis) && !(0.0..=1.0).contains(&v) {
                     ControlFlow::Break(())
                 } else {
                     ControlFlow::Continue(())
@@ -83,8 +252,43 @@ fn points_boundary_check<T: Visualize + Clone, U: UnitCellParameters>(
         .collect::<Vec<T>>()
 }
 
+/// Number of sites of each kind, e.g. `hollow_fcc: 2, bridge: 3`, followed by
+/// the multiplicities of the sites reduced by symmetry, e.g. `hollow_fcc: 2 (x9, x9)`
+fn site_kind_summary<T: CoordSite>(coord_sites: &[T]) -> String {
+    let mut multiplicities: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    coord_sites.iter().for_each(|site| {
+        let kind = site
+            .site_kind()
+            .map(|kind| kind.to_string())
+            .unwrap_or_else(|| site.site_type());
+        multiplicities
+            .entry(kind)
+            .or_default()
+            .push(site.multiplicity());
+    });
+    multiplicities
+        .iter()
+        .map(|(kind, multiplicity)| {
+            if multiplicity.iter().all(|&m| m == 1) {
+                format!("{kind}: {}", multiplicity.len())
+            } else {
+                let listed = multiplicity
+                    .iter()
+                    .map(|m| format!("x{m}"))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("{kind}: {} ({listed})", multiplicity.len())
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 fn export_filename<T: CoordSite>(coord_site: &T, task_config: &TaskTable) -> PathBuf {
-    let atom_ids_text = coord_site.connecting_atoms_msg();
+    let atom_ids_text = match coord_site.site_kind() {
+        Some(kind) => format!("{}_{}", kind, coord_site.connecting_atoms_msg()),
+        None => coord_site.connecting_atoms_msg(),
+    };
     let model_name = Path::new(task_config.model_path())
         .file_stem()
         .and_then(|s| s.to_str())
@@ -93,47 +297,422 @@ fn export_filename<T: CoordSite>(coord_site: &T, task_config: &TaskTable) -> Pat
 }
 
 fn export<T: CoordSite + Visualize, U: UnitCellParameters>(
-    base_model: &CellDocument,
+    base_model: &BaseModel,
     cell_param: &U,
     task_config: &TaskTable,
     coord_sites: &[T],
 ) -> Result<(), IoError> {
     let export_dir_path = Path::new(task_config.export_dir());
     if !export_dir_path.exists() {
-        create_dir_all(export_dir_path)?;
+        base_model.manifest().create_dir_all(export_dir_path)?;
     }
     coord_sites.iter().try_for_each(|site| {
         let filename = export_filename(site, task_config);
-        let mut new_model = base_model.clone();
         let new_pos_coordinate = site.fractional_coord(cell_param.lattice_bases());
-        let new_pos = IonicPosition::new(
-            task_config.new_element().symbol(),
-            new_pos_coordinate.into(),
-            None,
+        write_seed(
+            base_model,
+            task_config,
+            filename,
+            &[(task_config.new_element().symbol(), new_pos_coordinate)],
+            site,
+        )
+    })
+}
+
+/// Write the model with the new atoms, in fractional coordinates, as `.cell`,
+/// `.param` and `.cif`, as a `pw.x` input `.pwi`, or as a directory of the VASP inputs
+/// named by the seed. With the `per_seed` layout, the files go into the
+/// directory of the seed, with the `metadata.yaml` of the site.
+fn write_seed<T: CoordSite + Visualize>(
+    base_model: &BaseModel,
+    task_config: &TaskTable,
+    filename: PathBuf,
+    new_atoms: &[(ElementSymbol, Point3<f64>)],
+    site: &T,
+) -> Result<(), IoError> {
+    let filename = match task_config.export_layout() {
+        ExportLayout::PerSeed => {
+            let seed_dir = filename.with_extension("");
+            base_model.manifest().create_dir_all(&seed_dir)?;
+            write_metadata(base_model, task_config, &seed_dir, new_atoms, site)?;
+            match base_model.format {
+                ExportFormat::Vasp => filename,
+                ExportFormat::Castep | ExportFormat::QuantumEspresso => {
+                    seed_dir.join(filename.file_name().unwrap())
+                }
+            }
+        }
+        ExportLayout::Flat => filename,
+    };
+    let fixed = base_model.fixed_atoms().for_site(site.coordinating_atoms());
+    let num_base_atoms = base_model.cell().get_atom_data().symbols_repr().len();
+    let spins: Vec<(usize, f64)> = new_atoms
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &(symbol, _))| {
+            task_config
+                .initial_spin(symbol)
+                .map(|spin| (num_base_atoms + i, spin))
+        })
+        .collect();
+    let mut hubbard_u: Vec<(ElementSymbol, f64)> = Vec::new();
+    base_model
+        .cell()
+        .get_atom_data()
+        .symbols_repr()
+        .iter()
+        .chain(new_atoms.iter().map(|(symbol, _)| symbol))
+        .for_each(|&symbol| {
+            if hubbard_u.iter().all(|&(listed, _)| listed != symbol) {
+                if let Some(u) = task_config.hubbard_u(symbol) {
+                    hubbard_u.push((symbol, u));
+                }
+            }
+        });
+    if let (ExportFormat::Vasp, Some(poscar)) = (base_model.format, base_model.poscar.as_ref()) {
+        let new_poscar = poscar.with_fixed_atoms(&fixed).with_new_atoms(new_atoms);
+        return write_vasp_seed(
+            base_model,
+            &new_poscar,
+            task_config,
+            filename.with_extension(""),
+            &spins,
+            &hubbard_u,
         );
+    }
+    let manifest = base_model.manifest();
+    let mut new_model = base_model.cell().clone();
+    new_atoms.iter().for_each(|&(symbol, frac_coord)| {
+        let new_pos = IonicPosition::new(symbol, frac_coord.into(), None);
         new_model
             .model_description_mut()
             .ionic_pos_block_mut()
             .positions_mut()
             .push(new_pos);
-        let cif_file = to_cif_document(&new_model, filename.file_stem().unwrap().to_str().unwrap());
-        let cif_filename = filename.with_extension("cif");
-        new_model.write_out(filename)?;
-        write(cif_filename, cif_file.to_string())
-    })
+    });
+    let seed_name = filename.file_stem().unwrap().to_str().unwrap();
+    if let ExportFormat::QuantumEspresso = base_model.format {
+        let pw_input = pw_input(
+            &new_model,
+            seed_name,
+            base_model.kpoint_grid(),
+            task_config,
+            &fixed,
+            &spins,
+            &hubbard_u,
+        );
+        manifest.write(filename.with_extension("pwi"), pw_input)?;
+        return write_job_script(base_model, &filename, seed_name);
+    }
+    let cif_file = to_cif_document(&new_model, seed_name);
+    manifest.write(
+        filename.with_extension("param"),
+        castep_param(
+            task_config,
+            is_spin_polarized(&new_model) || !spins.is_empty(),
+        ),
+    )?;
+    manifest.write(filename.with_extension("cif"), cif_file.to_string())?;
+    manifest.write(
+        &filename,
+        seed_cell_content(
+            &new_model,
+            num_base_atoms,
+            base_model.kpoint_grid(),
+            &fixed,
+            &spins,
+            &hubbard_u,
+            base_model.potential_files(),
+        ),
+    )?;
+    write_job_script(base_model, &filename, seed_name)
+}
+
+/// The script of the seed beside its files, or the seed added to the array job.
+fn write_job_script(
+    base_model: &BaseModel,
+    filename: &Path,
+    seed_name: &str,
+) -> Result<(), IoError> {
+    let Some(job_scripts) = base_model.job_scripts() else {
+        return Ok(());
+    };
+    if job_scripts.is_array() {
+        base_model.seeds.borrow_mut().push(seed_name.to_string());
+        return Ok(());
+    }
+    base_model.manifest().write(
+        filename.with_file_name(format!("{seed_name}.{}", job_scripts.extension())),
+        job_scripts.seed_script(seed_name),
+    )
+}
+
+/// The array job over the exported seeds, `<model>_array.slurm` or `.pbs`,
+/// with the seeds listed in `<model>_seeds.txt`.
+pub fn write_array_job(base_model: &BaseModel, task_config: &TaskTable) -> Result<(), IoError> {
+    let Some(job_scripts) = base_model.job_scripts() else {
+        return Ok(());
+    };
+    let seeds = base_model.seeds.borrow();
+    if seeds.is_empty() {
+        return Ok(());
+    }
+    let model_name = Path::new(task_config.model_path())
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .expect("Invalid filename");
+    let export_dir = Path::new(task_config.export_dir());
+    let seed_list = format!("{model_name}_seeds.txt");
+    let content: String = seeds.iter().map(|seed| format!("{seed}\n")).collect();
+    base_model
+        .manifest()
+        .write(export_dir.join(&seed_list), content)?;
+    base_model.manifest().write(
+        export_dir.join(format!("{model_name}_array.{}", job_scripts.extension())),
+        job_scripts.array_script(model_name, &seed_list, seeds.len()),
+    )
+}
+
+#[derive(Serialize)]
+/// The site and the new atoms of a seed.
+struct SeedMetadata<'a> {
+    seed: &'a str,
+    model: &'a str,
+    export_format: ExportFormat,
+    site_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    site_kind: Option<String>,
+    coordinating_atoms: &'a [usize],
+    /// Cartesian coordinate (Å)
+    site_position: [f64; 3],
+    multiplicity: usize,
+    new_atoms: Vec<ElementSymbol>,
+}
+
+fn write_metadata<T: CoordSite + Visualize>(
+    base_model: &BaseModel,
+    task_config: &TaskTable,
+    seed_dir: &Path,
+    new_atoms: &[(ElementSymbol, Point3<f64>)],
+    site: &T,
+) -> Result<(), IoError> {
+    let site_position = site.determine_coord();
+    let metadata = SeedMetadata {
+        seed: seed_dir.file_name().unwrap().to_str().unwrap(),
+        model: task_config.model_path(),
+        export_format: base_model.format,
+        site_type: site.site_type(),
+        site_kind: site.site_kind().map(|kind| kind.to_string()),
+        coordinating_atoms: site.coordinating_atoms(),
+        site_position: [site_position.x, site_position.y, site_position.z],
+        multiplicity: site.multiplicity(),
+        new_atoms: new_atoms.iter().map(|&(symbol, _)| symbol).collect(),
+    };
+    let content = serde_yaml::to_string(&metadata).map_err(IoError::other)?;
+    base_model
+        .manifest()
+        .write(seed_dir.join("metadata.yaml"), content)
+}
+
+/// `POSCAR` with the new atoms appended to the species lines, with the
+/// `INCAR`, `KPOINTS` and `POTCAR.spec` stubs. The atoms without an initial
+/// spin take a zero `MAGMOM`.
+fn write_vasp_seed(
+    base_model: &BaseModel,
+    new_poscar: &Poscar,
+    task_config: &TaskTable,
+    seed_dir: PathBuf,
+    spins: &[(usize, f64)],
+    hubbard_u: &[(ElementSymbol, f64)],
+) -> Result<(), IoError> {
+    let manifest = base_model.manifest();
+    manifest.create_dir_all(&seed_dir)?;
+    let seed_name = seed_dir.file_name().unwrap().to_str().unwrap();
+    manifest.write(seed_dir.join("POSCAR"), new_poscar.to_string())?;
+    let mut magmom: Vec<f64> = Vec::new();
+    if !spins.is_empty() {
+        magmom = vec![0.0; new_poscar.num_atoms()];
+        spins.iter().for_each(|&(i, spin)| magmom[i] = spin);
+    }
+    manifest.write(
+        seed_dir.join("INCAR"),
+        incar(
+            seed_name,
+            task_config,
+            &magmom,
+            &ldau(new_poscar, hubbard_u),
+        ),
+    )?;
+    manifest.write(seed_dir.join("KPOINTS"), kpoints(base_model.kpoint_grid()))?;
+    manifest.write(seed_dir.join("POTCAR.spec"), potcar_spec(new_poscar))?;
+    write_job_script(base_model, &seed_dir.join(seed_name), seed_name)
+}
+
+/// Export every accepted pose of the adsorbate at the sites, named by the
+/// site with the index of the sampled rotation, e.g. `_rot3.cell`.
+/// # Returns
+/// Numbers of the exported poses at the multi, single and double sites.
+pub fn export_adsorbate_all<T: UnitCellParameters>(
+    base_model: &BaseModel,
+    cell_param: &T,
+    task_config: &TaskTable,
+    results: &SearchReports,
+    adsorbate: &Adsorbate,
+    site_index: &SiteIndex,
+    search_config: &SearchConfig,
+) -> Result<(usize, usize, usize), IoError> {
+    let periodicity = task_config.periodicity();
+    let mut num_multi = 0_usize;
+    let mut num_single = 0_usize;
+    let mut num_double = 0_usize;
+    let mut frames: Vec<String> = Vec::new();
+    if let Some(multi_points) = results.points() {
+        let boundary_checked: Vec<MultiCoordPoint> =
+            points_boundary_check(multi_points, cell_param, periodicity);
+        let (num_poses, pose_frames) = export_poses(
+            base_model,
+            cell_param,
+            task_config,
+            &boundary_checked,
+            adsorbate,
+            site_index,
+            search_config,
+        )?;
+        num_multi = num_poses;
+        frames.extend(pose_frames);
+        println!(
+            "Exported {num_multi} poses at multi-coordinated positions ({});",
+            site_kind_summary(&boundary_checked)
+        );
+    }
+    if let Some(single_points) = results.viable_single_points() {
+        let boundary_checked: Vec<DelegatePoint<1>> =
+            points_boundary_check(single_points, cell_param, periodicity);
+        let (num_poses, pose_frames) = export_poses(
+            base_model,
+            cell_param,
+            task_config,
+            &boundary_checked,
+            adsorbate,
+            site_index,
+            search_config,
+        )?;
+        num_single = num_poses;
+        frames.extend(pose_frames);
+        println!(
+            "Exported {num_single} poses at possible singly-coordinated positions ({});",
+            site_kind_summary(&boundary_checked)
+        );
+    }
+    if let Some(double_points) = results.viable_double_points() {
+        let boundary_checked: Vec<DelegatePoint<2>> =
+            points_boundary_check(double_points, cell_param, periodicity);
+        let (num_poses, pose_frames) = export_poses(
+            base_model,
+            cell_param,
+            task_config,
+            &boundary_checked,
+            adsorbate,
+            site_index,
+            search_config,
+        )?;
+        num_double = num_poses;
+        frames.extend(pose_frames);
+        println!(
+            "Exported {num_double} poses at possible doubly-coordinated positions ({});",
+            site_kind_summary(&boundary_checked)
+        );
+    }
+    write_extxyz(base_model, task_config, &frames)?;
+    Ok((num_multi, num_single, num_double))
+}
+
+/// # Returns
+/// Number of the exported poses, and their frames in the extended XYZ format
+/// when `extxyz` is set.
+fn export_poses<T: CoordSite + Visualize, U: UnitCellParameters>(
+    base_model: &BaseModel,
+    cell_param: &U,
+    task_config: &TaskTable,
+    coord_sites: &[T],
+    adsorbate: &Adsorbate,
+    site_index: &SiteIndex,
+    search_config: &SearchConfig,
+) -> Result<(usize, Vec<String>), IoError> {
+    let export_dir_path = Path::new(task_config.export_dir());
+    if !export_dir_path.exists() {
+        base_model.manifest().create_dir_all(export_dir_path)?;
+    }
+    let inverse_bases = cell_param
+        .lattice_bases()
+        .try_inverse()
+        .expect("Matrix is not invertible");
+    let rotations = task_config
+        .adsorbate()
+        .map(|spec| spec.rotations())
+        .unwrap_or(1);
+    // The other atoms of the adsorbate keep off the model by their own radii
+    let model_symbols = base_model.cell().get_atom_data().symbols_repr();
+    let exclusion_radii: HashMap<ElementSymbol, Vec<f64>> = adsorbate
+        .symbols()
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != adsorbate.anchor())
+        .map(|(_, &symbol)| {
+            let radii = model_symbols
+                .iter()
+                .map(|&s| default_exclusion_radius(symbol, s))
+                .collect();
+            (symbol, radii)
+        })
+        .collect();
+    let mut num_poses = 0_usize;
+    let mut frames: Vec<String> = Vec::new();
+    coord_sites.iter().try_for_each(|site| {
+        let poses = adsorbate.poses_at(
+            site.determine_coord(),
+            site.coordinating_atoms(),
+            site_index,
+            search_config,
+            &exclusion_radii,
+            rotations,
+        );
+        num_poses += poses.len();
+        let site_filename = export_filename(site, task_config);
+        let stem = site_filename.file_stem().unwrap().to_str().unwrap();
+        poses.iter().try_for_each(|pose| {
+            if task_config.extxyz() {
+                frames.push(extxyz_frame(
+                    base_model.cell(),
+                    site,
+                    pose.atoms(),
+                    task_config.periodicity(),
+                ));
+            }
+            let filename =
+                site_filename.with_file_name(format!("{}_rot{}.cell", stem, pose.rotation_index()));
+            let new_atoms: Vec<(ElementSymbol, Point3<f64>)> = pose
+                .atoms()
+                .iter()
+                .map(|&(symbol, p)| (symbol, inverse_bases * p))
+                .collect();
+            write_seed(base_model, task_config, filename, &new_atoms, site)
+        })
+    })?;
+    Ok((num_poses, frames))
 }
 
 fn collectively_export<T: CoordSite + Visualize + Debug, U: UnitCellParameters>(
-    base_model: &CellDocument,
+    base_model: &BaseModel,
     cell_param: &U,
     task_config: &TaskTable,
     coord_sites: &[T],
 ) -> Result<(), IoError> {
     let export_dir_path = Path::new(task_config.export_dir());
     if !export_dir_path.exists() {
-        create_dir(export_dir_path)?;
+        base_model.manifest().create_dir_all(export_dir_path)?;
     }
-    let mut new_model = base_model.clone();
+    let mut new_model = base_model.cell().clone();
     coord_sites.iter().for_each(|site| {
         let new_pos_coordinate = site.fractional_coord(cell_param.lattice_bases());
         let symbol = site.element_by_cn_number();
@@ -153,5 +732,123 @@ fn collectively_export<T: CoordSite + Visualize + Debug, U: UnitCellParameters>(
         model_name,
         coord_sites[0].site_type()
     ));
-    new_model.write_out(filename)
+    base_model.manifest().write(filename, new_model.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs::{read_to_string, remove_dir_all},
+        path::PathBuf,
+    };
+
+    use castep_periodic_table::element::ElementSymbol;
+    use chemrust_core::data::lattice::CrystalModel;
+    use chemrust_nasl::{CoordSite, MultiCoordPoint};
+    use nalgebra::Point3;
+
+    use crate::{
+        execution::{
+            constraints::FixedAtoms,
+            format_loader::load_cell_content,
+            manifest::ExportManifest,
+            test_fixtures::{task_in, PT_CO},
+        },
+        supportive_data::{ExportFormat, ExportLayout},
+    };
+
+    use super::{export, BaseModel};
+
+    const SEED: &str = "Pt_CO_multi_cn_2_0_1";
+
+    /// Export a bridge site of the two Pt atoms, standing for three, into a
+    /// new `export_dir`.
+    fn export_site(name: &str, format: ExportFormat, layout: ExportLayout) -> PathBuf {
+        let export_dir = std::env::temp_dir().join(format!("rhino_{name}_{}", std::process::id()));
+        let _ = remove_dir_all(&export_dir);
+        let mut task_config = task_in(&export_dir, None);
+        task_config.export_layout = layout;
+        let cell = load_cell_content(PT_CO.to_string()).unwrap();
+        let base_model = BaseModel::new(
+            cell.clone(),
+            format,
+            None,
+            [4, 4, 1],
+            FixedAtoms::new(&cell, &task_config),
+            ExportManifest::new(false),
+            None,
+        );
+        let site =
+            MultiCoordPoint::new(Point3::new(1.385, 0.0, 3.5), vec![0, 1]).with_multiplicity(3);
+        export(
+            &base_model,
+            cell.get_cell_parameters(),
+            &task_config,
+            &[site],
+        )
+        .unwrap();
+        export_dir
+    }
+
+    #[test]
+    fn per_seed_directories() {
+        let cases = [
+            (ExportFormat::Castep, vec!["cell", "param", "cif"]),
+            (ExportFormat::QuantumEspresso, vec!["pwi"]),
+        ];
+        cases.iter().for_each(|(format, extensions)| {
+            let export_dir = export_site(
+                &format!("per_seed_{format:?}"),
+                *format,
+                ExportLayout::PerSeed,
+            );
+            let seed_dir = export_dir.join(SEED);
+            extensions.iter().for_each(|extension| {
+                assert!(seed_dir.join(format!("{SEED}.{extension}")).is_file());
+                assert!(!export_dir.join(format!("{SEED}.{extension}")).exists());
+            });
+            assert!(seed_dir.join("metadata.yaml").is_file());
+            remove_dir_all(&export_dir).unwrap();
+        });
+        // The VASP seeds are directories already
+        let export_dir = export_site("per_seed_vasp", ExportFormat::Vasp, ExportLayout::PerSeed);
+        let seed_dir = export_dir.join(SEED);
+        ["POSCAR", "INCAR", "KPOINTS", "POTCAR.spec", "metadata.yaml"]
+            .iter()
+            .for_each(|file| assert!(seed_dir.join(file).is_file(), "{file}"));
+        assert!(!seed_dir.join(SEED).exists());
+        remove_dir_all(&export_dir).unwrap();
+        // Side by side, without the metadata
+        let export_dir = export_site("flat", ExportFormat::Castep, ExportLayout::Flat);
+        assert!(export_dir.join(format!("{SEED}.cell")).is_file());
+        assert!(!export_dir.join(SEED).exists());
+        remove_dir_all(&export_dir).unwrap();
+    }
+
+    #[test]
+    fn seed_metadata() {
+        let export_dir = export_site("metadata", ExportFormat::Castep, ExportLayout::PerSeed);
+        let content = read_to_string(export_dir.join(SEED).join("metadata.yaml")).unwrap();
+        let metadata: serde_yaml::Value = serde_yaml::from_str(&content).unwrap();
+        assert_eq!(metadata["seed"].as_str(), Some(SEED));
+        assert_eq!(metadata["model"].as_str(), Some("Pt_CO.cell"));
+        assert_eq!(metadata["export_format"].as_str(), Some("castep"));
+        assert_eq!(metadata["site_type"].as_str(), Some("multi"));
+        // Not classified
+        assert!(metadata.get("site_kind").is_none());
+        assert_eq!(
+            metadata["coordinating_atoms"],
+            serde_yaml::to_value([0, 1]).unwrap()
+        );
+        assert_eq!(
+            metadata["site_position"],
+            serde_yaml::to_value([1.385, 0.0, 3.5]).unwrap()
+        );
+        assert_eq!(metadata["multiplicity"].as_u64(), Some(3));
+        assert_eq!(
+            metadata["new_atoms"],
+            serde_yaml::to_value([ElementSymbol::O]).unwrap()
+        );
+        remove_dir_all(&export_dir).unwrap();
+    }
 }
@@ -0,0 +1,134 @@
+use castep_periodic_table::{
+    data::ELEMENT_TABLE,
+    element::{ElementSymbol, LookupElement},
+};
+use chemrust_core::data::{
+    atom::CoreAtomData,
+    geom::coordinates::CoordData,
+    lattice::{CrystalModel, UnitCellParameters},
+};
+use chemrust_nasl::{CoordSite, Periodicity, Visualize};
+use nalgebra::Point3;
+
+/// Frame of the model with the new atoms, in cartesian coordinates (Å), in
+/// the extended XYZ format. The site is described in the comment line by
+/// `site_type`, `coordination_number`, `coordinating_atoms` (indices in the
+/// model, counting from 0) and `site_position` (Å).
+pub fn extxyz_frame<M: CrystalModel, T: CoordSite + Visualize>(
+    model: &M,
+    site: &T,
+    new_atoms: &[(ElementSymbol, Point3<f64>)],
+    periodicity: Periodicity,
+) -> String {
+    let lattice_bases = model.get_cell_parameters().lattice_bases();
+    let symbols = model.get_atom_data().symbols_repr();
+    let lattice = lattice_bases
+        .column_iter()
+        .map(|v| format!("{:.8} {:.8} {:.8}", v[0], v[1], v[2]))
+        .collect::<Vec<String>>()
+        .join(" ");
+    let pbc = (0..3)
+        .map(|axis| {
+            if periodicity.is_periodic(axis) {
+                "T"
+            } else {
+                "F"
+            }
+        })
+        .collect::<Vec<&str>>()
+        .join(" ");
+    let coordinating_atoms = site
+        .coordinating_atoms()
+        .iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<String>>()
+        .join(" ");
+    let site_position = site.determine_coord();
+    let mut frame = format!(
+        "{}\nLattice=\"{lattice}\" Properties=species:S:1:pos:R:3 pbc=\"{pbc}\" site_type={} coordination_number={} coordinating_atoms=\"{coordinating_atoms}\" site_position=\"{:.8} {:.8} {:.8}\"",
+        symbols.len() + new_atoms.len(),
+        site.site_type(),
+        site.coordinating_atoms().len(),
+        site_position.x,
+        site_position.y,
+        site_position.z,
+    );
+    if let Some(kind) = site.site_kind() {
+        frame.push_str(&format!(" site_kind={kind}"));
+    }
+    frame.push_str(&format!(" multiplicity={}\n", site.multiplicity()));
+    let positions: Vec<Point3<f64>> = model
+        .get_atom_data()
+        .coords_repr()
+        .iter()
+        .map(|cd| match cd {
+            CoordData::Fractional(frac) => lattice_bases * frac,
+            CoordData::Cartesian(cart) => *cart,
+        })
+        .collect();
+    symbols
+        .iter()
+        .zip(positions.iter())
+        .chain(new_atoms.iter().map(|(symbol, p)| (symbol, p)))
+        .for_each(|(&symbol, p)| {
+            frame.push_str(&format!(
+                "{:<3}{:16.8}{:16.8}{:16.8}\n",
+                ELEMENT_TABLE.get_by_symbol(symbol).symbol_to_string(),
+                p.x,
+                p.y,
+                p.z
+            ))
+        });
+    frame
+}
+
+#[cfg(test)]
+mod test {
+    use castep_periodic_table::element::ElementSymbol;
+    use chemrust_nasl::{DelegatePoint, Periodicity, Visualize};
+    use nalgebra::Point3;
+
+    use crate::execution::format_loader::load_cell_content;
+
+    use super::extxyz_frame;
+
+    #[test]
+    fn frame_of_site() {
+        let model = load_cell_content(
+            "%BLOCK LATTICE_CART
+4.0 0.0 0.0
+0.0 4.0 0.0
+0.0 0.0 20.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+Pt 0.0 0.0 0.1
+Pt 0.5 0.0 0.1
+%ENDBLOCK POSITIONS_FRAC
+"
+            .to_string(),
+        )
+        .unwrap();
+        let site = DelegatePoint::<2>::new(Point3::new(1.0, 0.0, 3.5), [0, 1]);
+        let new_atoms = [
+            (ElementSymbol::O, site.determine_coord()),
+            (ElementSymbol::H, Point3::new(1.0, 0.0, 4.5)),
+        ];
+        let periodicity = Periodicity::new(true, true, false);
+        let frame = extxyz_frame(&model, &site, &new_atoms, periodicity);
+        let lines: Vec<&str> = frame.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "4");
+        assert!(lines[1].contains("pbc=\"T T F\""));
+        assert!(lines[1].contains("coordination_number=2"));
+        assert!(lines[1].contains("coordinating_atoms=\"0 1\""));
+        assert!(lines[1].contains("site_position=\"1.00000000 0.00000000 3.50000000\""));
+        assert!(lines[1].ends_with("multiplicity=1"));
+        // The model atoms in cartesian coordinates, then the new atoms
+        assert!(lines[3].starts_with("Pt"));
+        assert!(lines[3].contains("2.00000000"));
+        assert!(lines[4].starts_with("O"));
+        assert!(lines[5].starts_with("H"));
+        assert!(lines[5].ends_with("4.50000000"));
+    }
+}
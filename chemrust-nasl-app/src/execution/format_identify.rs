@@ -8,6 +8,9 @@ use crate::error::FormatError;
 #[derive(Debug, Clone, Copy)]
 pub enum AcceptFormat {
     Cell,
+    Cif,
+    /// VASP `POSCAR`/`CONTCAR`
+    Poscar,
 }
 
 pub enum ModelFormat {
@@ -16,7 +19,16 @@ pub enum ModelFormat {
 
 pub struct Model<T: CrystalModel>(pub(crate) T);
 
+/// The format by the extension, or by the VASP file names, e.g. `CONTCAR` or `POSCAR_relaxed`.
 pub fn match_format<P: AsRef<Path>>(file_path: &P) -> Result<AcceptFormat, FormatError> {
+    let is_vasp_name = file_path
+        .as_ref()
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with("POSCAR") || name.starts_with("CONTCAR"));
+    if is_vasp_name {
+        return Ok(AcceptFormat::Poscar);
+    }
     let suffix = file_path
         .as_ref()
         .extension()
@@ -25,6 +37,8 @@ pub fn match_format<P: AsRef<Path>>(file_path: &P) -> Result<AcceptFormat, Forma
         .unwrap();
     match suffix {
         "cell" => Ok(AcceptFormat::Cell),
+        "cif" => Ok(AcceptFormat::Cif),
+        "vasp" | "poscar" => Ok(AcceptFormat::Poscar),
         _ => Err(FormatError::Supported),
     }
 }
@@ -0,0 +1,57 @@
+use castep_cell_io::{
+    CellDocument, CellParseError, CellParser, IonicPosition, IonicPositionBlock, LatticeCart,
+    LatticeParam, LatticeParamBlock, LengthUnit, PositionsKeywords,
+};
+use castep_periodic_table::element::ElementSymbol;
+use chemrust_core::data::lattice::CrystalModel;
+use nalgebra::{Matrix3, Point3};
+
+/// The bare lattice and positions blocks a `.cell` document needs to be read.
+/// `castep-cell-io` does not export `CellEssentials`, so the document starts
+/// from this and takes the blocks of the model in place of these.
+const BARE_CELL: &str = "%BLOCK LATTICE_CART
+1.0 0.0 0.0
+0.0 1.0 0.0
+0.0 0.0 1.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+H 0.0 0.0 0.0
+%ENDBLOCK POSITIONS_FRAC
+";
+
+/// A `.cell` model of the lattice (vectors as columns, in Å) and the atoms in
+/// fractional coordinates, without any other blocks.
+pub(super) fn new_cell_document(
+    lattice_bases: &Matrix3<f64>,
+    atoms: &[(ElementSymbol, Point3<f64>)],
+) -> CellDocument {
+    let mut cell = CellParser::from(&BARE_CELL)
+        .parse()
+        .expect("The bare `.cell` content is valid");
+    let [a, b, c]: [[f64; 3]; 3] = [0, 1, 2].map(|i| lattice_bases.column(i).into());
+    *cell.get_cell_parameters_mut() = LatticeParamBlock::new(
+        LengthUnit::Ang,
+        LatticeParam::LatticeCart(LatticeCart::new(a, b, c)),
+    );
+    let positions = atoms
+        .iter()
+        .map(|(symbol, frac)| IonicPosition::new(*symbol, (*frac).into(), None))
+        .collect();
+    // Spin-polarised as the parsed `.cell` models are
+    *cell.get_atom_data_mut() = IonicPositionBlock::new(
+        LengthUnit::Ang,
+        positions,
+        PositionsKeywords::POSITIONS_FRAC,
+        true,
+    );
+    cell.set_entries(None);
+    cell
+}
+
+/// A bare `.cell` document holding the entries read from the blocks and
+/// keywords in the `content`, e.g. `KPOINTS_MP_GRID : 3 3 1`. `castep-cell-io`
+/// does not export its entries, so they are built by its parser.
+pub fn entries_document(content: &str) -> Result<CellDocument, CellParseError> {
+    CellParser::from(&format!("{BARE_CELL}\n{content}")).parse()
+}
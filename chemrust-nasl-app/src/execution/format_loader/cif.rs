@@ -0,0 +1,497 @@
+use castep_cell_io::CellDocument;
+use castep_periodic_table::element::{Element, ElementSymbol};
+use crystal_cif_io::{
+    CifDocument, DataBlock, DataBlockMember, DataItems, LoopColumn, Number, Value,
+};
+use nalgebra::{Matrix3, Point3, Vector3};
+
+use crate::error::FormatError;
+
+use super::cell_model::new_cell_document;
+
+/// Atoms of the same element closer than this (Å) after the symmetry
+/// expansion are the same atom on a special position.
+const DUPLICATE_TOLERANCE: f64 = 1e-2;
+
+/// Read the first data block of the CIF as a `.cell` model, with the symmetry
+/// operations expanded to the full P1 cell.
+pub(crate) fn cif_to_cell_document(content: &str) -> Result<CellDocument, FormatError> {
+    let data_block = first_data_block(content)?;
+    let lattice_bases = lattice_bases(&data_block).ok_or(FormatError::Compatible)?;
+    let operations = symmetry_operations(&data_block)?;
+    let asymmetric_unit = atom_sites(&data_block)?;
+    let atoms = expand_atoms(&asymmetric_unit, &operations, &lattice_bases);
+    Ok(new_cell_document(&lattice_bases, &atoms))
+}
+
+/// The first data block, with every loop checked to have whole rows.
+fn first_data_block(content: &str) -> Result<DataBlock, FormatError> {
+    // `crystal-cif-io` cannot take a loop whose values stop in the middle of
+    // a row, so the rows are counted on the text first.
+    if !complete_loops(content) {
+        return Err(FormatError::IncompleteLoop);
+    }
+    let mut input = content;
+    CifDocument::parse_from_str(&mut input)
+        .ok()
+        .as_ref()
+        .and_then(|document| document.data_blocks())
+        .and_then(|blocks| blocks.first())
+        .cloned()
+        .ok_or(FormatError::Compatible)
+}
+
+/// Whether the values of every `loop_` fill whole rows of its tags.
+fn complete_loops(content: &str) -> bool {
+    let mut tokens = cif_tokens(content).into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        if !token.eq_ignore_ascii_case("loop_") {
+            continue;
+        }
+        let mut num_tags = 0_usize;
+        while tokens.next_if(|token| token.starts_with('_')).is_some() {
+            num_tags += 1;
+        }
+        let mut num_values = 0_usize;
+        while tokens.next_if(|token| !is_reserved(token)).is_some() {
+            num_values += 1;
+        }
+        if num_tags == 0 || num_values % num_tags != 0 {
+            return false;
+        }
+    }
+    true
+}
+
+/// Tags and the reserved words ending the values of a loop.
+fn is_reserved(token: &str) -> bool {
+    let lowercase = token.to_lowercase();
+    token.starts_with('_')
+        || lowercase == "loop_"
+        || lowercase == "global_"
+        || lowercase == "stop_"
+        || lowercase.starts_with("data_")
+        || lowercase.starts_with("save_")
+}
+
+/// The tags, reserved words and values of the CIF, without the comments.
+/// A quoted string keeps its quotes, and a text field between the lines
+/// starting with `;` is taken as a single `;`.
+fn cif_tokens(content: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut in_text_field = false;
+    content.lines().for_each(|line| {
+        let mut rest = match line.strip_prefix(';') {
+            Some(_) if !in_text_field => {
+                in_text_field = true;
+                tokens.push(";");
+                return;
+            }
+            Some(after) => {
+                in_text_field = false;
+                after
+            }
+            None if in_text_field => return,
+            None => line,
+        };
+        loop {
+            rest = rest.trim_start();
+            let Some(first) = rest.chars().next() else {
+                break;
+            };
+            if first == '#' {
+                break;
+            }
+            let end = match first {
+                // Closed by the quote followed by a whitespace or the end of the line
+                '\'' | '"' => rest
+                    .char_indices()
+                    .skip(1)
+                    .find(|&(i, c)| {
+                        c == first && !rest[i + 1..].starts_with(|c: char| !c.is_whitespace())
+                    })
+                    .map(|(i, _)| i + 1)
+                    .unwrap_or(rest.len()),
+                _ => rest.find(char::is_whitespace).unwrap_or(rest.len()),
+            };
+            tokens.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+    });
+    tokens
+}
+
+/// The columns of each loop in the data block.
+fn loops<'a>(data_block: &'a DataBlock) -> impl Iterator<Item = &'a [LoopColumn]> {
+    data_block
+        .members()
+        .iter()
+        .filter_map(|member| match member {
+            DataBlockMember::DataItems(DataItems::MultiValues(columns)) => Some(columns.columns()),
+            _ => None,
+        })
+}
+
+/// Tags are compared without the leading `_` and the case, as CIF does.
+fn same_tag(found: &str, tag: &str) -> bool {
+    found
+        .trim_start_matches('_')
+        .eq_ignore_ascii_case(tag.trim_start_matches('_'))
+}
+
+fn single_value<'a>(data_block: &'a DataBlock, tag: &str) -> Option<&'a Value> {
+    data_block.members().iter().find_map(|member| match member {
+        DataBlockMember::DataItems(DataItems::SingleValue(item))
+            if same_tag(item.tag().as_str(), tag) =>
+        {
+            Some(item.value())
+        }
+        _ => None,
+    })
+}
+
+/// Rows of the loop containing the first of the `tags` found, with the values
+/// in the order of the `tags`; `None` for the columns missing in the loop.
+fn loop_rows<'a>(data_block: &'a DataBlock, tags: &[&str]) -> Option<Vec<Vec<Option<&'a Value>>>> {
+    let columns = loops(data_block).find(|columns| {
+        columns
+            .iter()
+            .any(|column| tags.iter().any(|tag| same_tag(column.tag().as_str(), tag)))
+    })?;
+    let picked: Vec<Option<&LoopColumn>> = tags
+        .iter()
+        .map(|tag| {
+            columns
+                .iter()
+                .find(|column| same_tag(column.tag().as_str(), tag))
+        })
+        .collect();
+    let num_rows = columns.first().map_or(0, |column| column.values().len());
+    Some(
+        (0..num_rows)
+            .map(|i| {
+                picked
+                    .iter()
+                    .map(|column| column.map(|c| &c.values()[i]))
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+/// Numbers with the standard uncertainty dropped, e.g. `5.431(2)`.
+fn number(value: &Value) -> Option<f64> {
+    match value.as_numeric()?.number() {
+        // Through the shortest text of the `f32`, so `5.431` stays `5.431`
+        Number::Float(float) => float.0.to_string().parse().ok(),
+        Number::Integer(integer) => Some(f64::from(integer.0)),
+    }
+}
+
+fn text(value: &Value) -> Option<&str> {
+    value.as_char_string().map(AsRef::as_ref)
+}
+
+/// Lattice vectors as columns, with `a` along `x` and `b` in the `xy` plane.
+fn lattice_bases(data_block: &DataBlock) -> Option<Matrix3<f64>> {
+    let length = |tag: &str| single_value(data_block, tag).and_then(number);
+    let a = length("_cell_length_a")?;
+    let b = length("_cell_length_b")?;
+    let c = length("_cell_length_c")?;
+    let [alpha, beta, gamma] = ["_cell_angle_alpha", "_cell_angle_beta", "_cell_angle_gamma"]
+        .map(|tag| length(tag).unwrap_or(90.0).to_radians());
+    let cx = c * beta.cos();
+    let cy = c * (alpha.cos() - beta.cos() * gamma.cos()) / gamma.sin();
+    let cz = (c * c - cx * cx - cy * cy).sqrt();
+    Some(Matrix3::from_columns(&[
+        Vector3::new(a, 0.0, 0.0),
+        Vector3::new(b * gamma.cos(), b * gamma.sin(), 0.0),
+        Vector3::new(cx, cy, cz),
+    ]))
+}
+
+/// Operations in the `x, y, z` form. Without them, only a CIF naming the
+/// space group P1 is read, as the identity alone.
+fn symmetry_operations(
+    data_block: &DataBlock,
+) -> Result<Vec<(Matrix3<f64>, Vector3<f64>)>, FormatError> {
+    let rows = loop_rows(
+        data_block,
+        &[
+            "_space_group_symop_operation_xyz",
+            "_symmetry_equiv_pos_as_xyz",
+        ],
+    );
+    match rows {
+        Some(rows) => rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .flatten()
+                    .next()
+                    .and_then(|op| text(op))
+                    .and_then(parse_symop)
+                    .ok_or(FormatError::Compatible)
+            })
+            .collect(),
+        None if names_p1(data_block) => Ok(vec![(Matrix3::identity(), Vector3::zeros())]),
+        None => Err(FormatError::MissingSymmetryOps),
+    }
+}
+
+/// Whether the space group is given, and given as P1, by its symbol or number.
+fn names_p1(data_block: &DataBlock) -> bool {
+    let symbol = [
+        "_space_group_name_H-M_alt",
+        "_symmetry_space_group_name_H-M",
+        "_space_group_name_Hall",
+        "_symmetry_space_group_name_Hall",
+    ]
+    .iter()
+    .find_map(|tag| single_value(data_block, tag).and_then(text));
+    let number = ["_space_group_IT_number", "_symmetry_Int_Tables_number"]
+        .iter()
+        .find_map(|tag| single_value(data_block, tag).and_then(number));
+    (symbol.is_some() || number.is_some())
+        && symbol.iter().all(|s| {
+            s.split_whitespace()
+                .collect::<String>()
+                .eq_ignore_ascii_case("P1")
+        })
+        && number.iter().all(|&n| n == 1.0)
+}
+
+/// Parse an operation like `-x+1/2, y-x, z` into its rotation and translation.
+fn parse_symop(op: &str) -> Option<(Matrix3<f64>, Vector3<f64>)> {
+    let parts: Vec<String> = op
+        .split(',')
+        .map(|part| {
+            part.chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let mut rotation = Matrix3::zeros();
+    let mut translation = Vector3::zeros();
+    for (row, part) in parts.iter().enumerate() {
+        let mut terms: Vec<String> = Vec::new();
+        part.chars().for_each(|c| match terms.last_mut() {
+            Some(term) if c != '+' && c != '-' => term.push(c),
+            _ => terms.push(c.to_string()),
+        });
+        for term in terms.iter() {
+            let (sign, body) = match term.strip_prefix('-') {
+                Some(body) => (-1.0, body),
+                None => (1.0, term.strip_prefix('+').unwrap_or(term)),
+            };
+            match body.chars().last() {
+                Some(axis @ ('x' | 'y' | 'z')) => {
+                    let column = (axis as u8 - b'x') as usize;
+                    let coefficient = body[..body.len() - 1].trim_end_matches('*');
+                    let coefficient = if coefficient.is_empty() {
+                        1.0
+                    } else {
+                        parse_fraction(coefficient)?
+                    };
+                    rotation[(row, column)] += sign * coefficient;
+                }
+                _ => translation[row] += sign * parse_fraction(body)?,
+            }
+        }
+    }
+    Some((rotation, translation))
+}
+
+fn parse_fraction(value: &str) -> Option<f64> {
+    match value.split_once('/') {
+        Some((numerator, denominator)) => {
+            Some(numerator.parse::<f64>().ok()? / denominator.parse::<f64>().ok()?)
+        }
+        None => value.parse().ok(),
+    }
+}
+
+/// Element symbols and fractional coordinates of the atoms in the asymmetric unit.
+fn atom_sites(data_block: &DataBlock) -> Result<Vec<(ElementSymbol, Point3<f64>)>, FormatError> {
+    let rows = loop_rows(
+        data_block,
+        &[
+            "_atom_site_fract_x",
+            "_atom_site_fract_y",
+            "_atom_site_fract_z",
+            "_atom_site_type_symbol",
+            "_atom_site_label",
+        ],
+    )
+    .ok_or(FormatError::Compatible)?;
+    rows.iter()
+        .map(|row| {
+            let xyz: Vec<f64> = row[..3]
+                .iter()
+                .map(|v| v.and_then(number).ok_or(FormatError::Compatible))
+                .collect::<Result<Vec<f64>, FormatError>>()?;
+            let symbol = row[3]
+                .and_then(text)
+                .and_then(element_symbol)
+                .or_else(|| row[4].and_then(text).and_then(element_symbol))
+                .ok_or(FormatError::Compatible)?;
+            Ok((symbol, Point3::new(xyz[0], xyz[1], xyz[2])))
+        })
+        .collect()
+}
+
+/// The element in a type symbol or label, e.g. `Fe` of `Fe2+` or `Fe1`.
+fn element_symbol(value: &str) -> Option<ElementSymbol> {
+    let mut chars = value.chars().skip_while(|c| !c.is_ascii_alphabetic());
+    let first = chars.next()?.to_ascii_uppercase();
+    let symbol: String = std::iter::once(first)
+        .chain(chars.next().filter(|c| c.is_ascii_lowercase()))
+        .collect();
+    // Labels like `Os1` are `Os`, but `O1a` is `O`.
+    [symbol, first.to_string()]
+        .iter()
+        .find_map(|s| s.parse::<Element>().ok())
+        .map(|element| element.symbol())
+}
+
+/// Apply the operations to the atoms, wrap them into the cell, and drop the
+/// images on the same positions.
+fn expand_atoms(
+    asymmetric_unit: &[(ElementSymbol, Point3<f64>)],
+    operations: &[(Matrix3<f64>, Vector3<f64>)],
+    lattice_bases: &Matrix3<f64>,
+) -> Vec<(ElementSymbol, Point3<f64>)> {
+    let mut atoms: Vec<(ElementSymbol, Point3<f64>)> = Vec::new();
+    asymmetric_unit.iter().for_each(|(symbol, frac)| {
+        operations.iter().for_each(|(rotation, translation)| {
+            let image = (rotation * frac + *translation).map(|v| {
+                let v = v - v.floor();
+                if 1.0 - v < 1e-8 {
+                    0.0
+                } else {
+                    v
+                }
+            });
+            let duplicated = atoms.iter().any(|(s, p)| {
+                let diff = (*p - image).map(|d| d - d.round());
+                s == symbol && (lattice_bases * diff).norm() < DUPLICATE_TOLERANCE
+            });
+            if !duplicated {
+                atoms.push((*symbol, image));
+            }
+        })
+    });
+    atoms
+}
+
+#[cfg(test)]
+mod test {
+    use castep_periodic_table::element::ElementSymbol;
+    use chemrust_core::data::{atom::CoreAtomData, lattice::CrystalModel};
+
+    use crate::error::FormatError;
+
+    use super::{cif_to_cell_document, complete_loops, first_data_block, parse_symop};
+
+    const ROCKSALT: &str = "data_NaCl
+_cell_length_a 5.64(1)
+_cell_length_b 5.64
+_cell_length_c 5.64
+_symmetry_space_group_name_H-M 'F m -3 m'
+loop_
+_symmetry_equiv_pos_as_xyz
+'x, y, z'
+'x+1/2, y+1/2, z'
+'x+1/2, y, z+1/2'
+'x, y+1/2, z+1/2'
+'-x, -y, -z'
+loop_
+_atom_site_label
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+Na1 0.0 0.0 0.0
+Cl1 0.5 0.5 0.5
+";
+
+    #[test]
+    fn expand_rocksalt() {
+        let data_block = first_data_block(ROCKSALT).unwrap();
+        let lattice = super::lattice_bases(&data_block).unwrap();
+        assert_eq!(lattice[(0, 0)], 5.64);
+        let operations = super::symmetry_operations(&data_block).unwrap();
+        assert_eq!(operations.len(), 5);
+        let atoms = super::atom_sites(&data_block).unwrap();
+        assert_eq!(atoms[1].0, ElementSymbol::Cl);
+        let cell = cif_to_cell_document(ROCKSALT).unwrap();
+        // Inversion maps every atom onto an existing one
+        assert_eq!(cell.get_atom_data().symbols_repr().len(), 8);
+        // Nothing but the lattice and the positions
+        assert!(cell.other_entries().is_none());
+        let (rotation, translation) = parse_symop("y-x, -x+1/2, 0.25-z").unwrap();
+        assert_eq!(rotation[(0, 0)], -1.0);
+        assert_eq!(rotation[(0, 1)], 1.0);
+        assert_eq!(translation, nalgebra::Vector3::new(0.0, 0.5, 0.25));
+    }
+
+    #[test]
+    fn incomplete_cif() {
+        // The last row misses its `z`
+        let partial_row = ROCKSALT.replace("Cl1 0.5 0.5 0.5", "Cl1 0.5 0.5");
+        assert!(matches!(
+            cif_to_cell_document(&partial_row),
+            Err(FormatError::IncompleteLoop)
+        ));
+        let without_ops = ROCKSALT.replace(
+            "loop_
+_symmetry_equiv_pos_as_xyz
+'x, y, z'
+'x+1/2, y+1/2, z'
+'x+1/2, y, z+1/2'
+'x, y+1/2, z+1/2'
+'-x, -y, -z'
+",
+            "",
+        );
+        assert!(matches!(
+            cif_to_cell_document(&without_ops),
+            Err(FormatError::MissingSymmetryOps)
+        ));
+        // Without the operations, only P1 is read
+        let p1 = without_ops.replace("'F m -3 m'", "'P 1'");
+        let cell = cif_to_cell_document(&p1).unwrap();
+        assert_eq!(cell.get_atom_data().symbols_repr().len(), 2);
+    }
+
+    #[test]
+    fn loop_rows_on_text() {
+        assert!(complete_loops(
+            "data_test
+loop_
+_atom_site_label
+_atom_site_type_symbol # comment
+'Na 1' Na
+\"Cl's\" Cl
+;
+a text field
+  over lines
+;
+Cl
+"
+        ));
+        assert!(!complete_loops(
+            "data_test
+loop_
+_atom_site_label
+_atom_site_type_symbol
+'Na 1' Na
+Cl1
+_cell_length_a 5.64
+"
+        ));
+    }
+}
@@ -1,8 +1,30 @@
 use std::{fs::read_to_string, path::Path};
 
 use castep_cell_io::{CellDocument, CellParser};
+use castep_periodic_table::element::{Element, ElementSymbol};
+use chemrust_core::data::{
+    atom::CoreAtomData,
+    geom::coordinates::CoordData,
+    lattice::{CrystalModel, UnitCellParameters},
+};
+use chemrust_nasl::Adsorbate;
+use nalgebra::Point3;
 
-use crate::error::FormatError;
+use crate::{
+    error::{FormatError, RunError},
+    supportive_data::{AdsorbateFragment, AdsorbateSpec},
+};
+
+use self::cif::cif_to_cell_document;
+
+use super::format_identify::{match_format, AcceptFormat};
+
+mod cell_model;
+mod cif;
+mod poscar;
+
+pub use cell_model::entries_document;
+pub use poscar::Poscar;
 
 pub fn load_cell_file<P: AsRef<Path>>(cell_path: P) -> Result<CellDocument, FormatError> {
     let content = read_to_string(cell_path).map_err(|_| FormatError::ReadToString)?;
@@ -16,3 +38,108 @@ pub fn load_cell_content(cell_content: String) -> Result<CellDocument, FormatErr
         .parse()
         .map_err(|_| FormatError::Compatible)
 }
+
+/// Load the `.cif` as a `.cell` model, with the atoms expanded by the symmetry
+/// operations to the full cell.
+pub fn load_cif_file<P: AsRef<Path>>(cif_path: P) -> Result<CellDocument, FormatError> {
+    let content = read_to_string(cif_path).map_err(|_| FormatError::ReadToString)?;
+    cif_to_cell_document(&content)
+}
+
+pub fn load_poscar_file<P: AsRef<Path>>(poscar_path: P) -> Result<Poscar, FormatError> {
+    let content = read_to_string(poscar_path).map_err(|_| FormatError::ReadToString)?;
+    Poscar::parse(&content)
+}
+
+/// Load the model of any accepted format as a `.cell` model.
+pub fn load_model_file<P: AsRef<Path>>(model_path: P) -> Result<CellDocument, FormatError> {
+    match match_format(&model_path)? {
+        AcceptFormat::Cell => load_cell_file(model_path),
+        AcceptFormat::Cif => load_cif_file(model_path),
+        AcceptFormat::Poscar => load_poscar_file(model_path)?.to_cell_document(),
+    }
+}
+
+pub fn load_adsorbate(spec: &AdsorbateSpec) -> Result<Adsorbate, RunError> {
+    match spec {
+        AdsorbateSpec::Preset(name) => spec
+            .preset()
+            .map(|preset| preset.to_adsorbate())
+            .ok_or_else(|| RunError::Message(format!("Unknown adsorbate preset `{name}`"))),
+        AdsorbateSpec::Fragment(fragment) => load_adsorbate_fragment(fragment),
+    }
+}
+
+/// Load the molecule from an `.xyz` or `.cell` fragment.
+fn load_adsorbate_fragment(fragment: &AdsorbateFragment) -> Result<Adsorbate, RunError> {
+    let path = Path::new(fragment.path());
+    let suffix = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .ok_or(RunError::FormatError(FormatError::Identified))?;
+    let (symbols, coords) = match suffix {
+        "xyz" => {
+            let content = read_to_string(path)
+                .map_err(|_| RunError::FormatError(FormatError::ReadToString))?;
+            parse_xyz(&content).map_err(RunError::FormatError)?
+        }
+        "cell" => {
+            let cell = load_cell_file(path).map_err(RunError::FormatError)?;
+            let lattice_bases = cell.get_cell_parameters().lattice_bases();
+            let coords = cell
+                .get_atom_data()
+                .coords_repr()
+                .iter()
+                .map(|cd| match cd {
+                    CoordData::Fractional(frac) => lattice_bases * frac,
+                    CoordData::Cartesian(cart) => *cart,
+                })
+                .collect();
+            (cell.get_atom_data().symbols_repr().to_vec(), coords)
+        }
+        _ => return Err(RunError::FormatError(FormatError::Supported)),
+    };
+    if fragment.anchor() >= symbols.len() {
+        return Err(RunError::Message(format!(
+            "The anchor index {} is out of the {} atoms in {}",
+            fragment.anchor(),
+            symbols.len(),
+            fragment.path()
+        )));
+    }
+    Ok(Adsorbate::new(symbols, &coords, fragment.anchor()))
+}
+
+/// Atoms from the `.xyz` content: the number of atoms, a comment line, then
+/// `symbol x y z` per line in Å.
+fn parse_xyz(content: &str) -> Result<(Vec<ElementSymbol>, Vec<Point3<f64>>), FormatError> {
+    let mut lines = content.lines();
+    let num_atoms: usize = lines
+        .next()
+        .and_then(|line| line.trim().parse().ok())
+        .ok_or(FormatError::Compatible)?;
+    let atoms = lines
+        .skip(1)
+        .take(num_atoms)
+        .map(|line| {
+            let mut fields = line.split_whitespace();
+            let symbol = fields
+                .next()
+                .and_then(|s| s.parse::<Element>().ok())
+                .ok_or(FormatError::Compatible)?
+                .symbol();
+            let xyz: Vec<f64> = fields
+                .take(3)
+                .map(|v| v.parse::<f64>().map_err(|_| FormatError::Compatible))
+                .collect::<Result<Vec<f64>, FormatError>>()?;
+            if xyz.len() < 3 {
+                return Err(FormatError::Compatible);
+            }
+            Ok((symbol, Point3::new(xyz[0], xyz[1], xyz[2])))
+        })
+        .collect::<Result<Vec<(ElementSymbol, Point3<f64>)>, FormatError>>()?;
+    if atoms.len() < num_atoms {
+        return Err(FormatError::Compatible);
+    }
+    Ok(atoms.into_iter().unzip())
+}
@@ -0,0 +1,307 @@
+use std::fmt::Display;
+
+use castep_cell_io::CellDocument;
+use castep_periodic_table::{
+    data::ELEMENT_TABLE,
+    element::{Element, ElementSymbol, LookupElement},
+};
+use chemrust_core::data::{
+    atom::CoreAtomData,
+    geom::coordinates::CoordData,
+    lattice::{CrystalModel, UnitCellParameters},
+};
+use nalgebra::{Matrix3, Point3, Vector3};
+
+use crate::error::FormatError;
+
+use super::cell_model::new_cell_document;
+
+/// A VASP `POSCAR`/`CONTCAR` structure, with the scaling factor applied to the lattice.
+#[derive(Debug, Clone)]
+pub struct Poscar {
+    comment: String,
+    /// Lattice vectors as columns
+    lattice_bases: Matrix3<f64>,
+    /// Element and number of atoms of each group, as in the species lines
+    species: Vec<(String, usize)>,
+    frac_coords: Vec<Point3<f64>>,
+    /// Whether each atom may move along `x, y, z`, when selective dynamics is on
+    selective_dynamics: Option<Vec<[bool; 3]>>,
+}
+
+impl Poscar {
+    /// Parse the VASP 5 format, or the VASP 4 format with the elements given
+    /// in the comment line.
+    pub fn parse(content: &str) -> Result<Self, FormatError> {
+        let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+        let mut next_line = || lines.next().ok_or(FormatError::Compatible);
+        let comment = next_line()?.trim().to_string();
+        let scale: f64 = next_line()?
+            .split_whitespace()
+            .next()
+            .and_then(|v| v.parse().ok())
+            .ok_or(FormatError::Compatible)?;
+        let mut vectors: Vec<Vector3<f64>> = Vec::new();
+        for _ in 0..3 {
+            vectors.push(parse_vector(next_line()?)?);
+        }
+        let lattice_bases = Matrix3::from_columns(&vectors);
+        // A negative scaling factor is the volume of the cell.
+        let scale = if scale < 0.0 {
+            (scale.abs() / lattice_bases.determinant().abs()).cbrt()
+        } else {
+            scale
+        };
+        let lattice_bases = lattice_bases * scale;
+        let mut line = next_line()?;
+        let names: Vec<String> = if line.split_whitespace().all(|v| v.parse::<usize>().is_err()) {
+            let names = line.split_whitespace().map(element_name).collect();
+            line = next_line()?;
+            names
+        } else {
+            comment.split_whitespace().map(element_name).collect()
+        };
+        let counts: Vec<usize> = line
+            .split_whitespace()
+            .map(|v| v.parse().map_err(|_| FormatError::Compatible))
+            .collect::<Result<Vec<usize>, FormatError>>()?;
+        if names.len() < counts.len() {
+            return Err(FormatError::Compatible);
+        }
+        let species: Vec<(String, usize)> = names.into_iter().zip(counts).collect();
+        let mut line = next_line()?.trim_start();
+        let selective = line.starts_with(['S', 's']);
+        if selective {
+            line = next_line()?.trim_start();
+        }
+        let cartesian = line.starts_with(['C', 'c', 'K', 'k']);
+        let inverse_bases = lattice_bases.try_inverse().ok_or(FormatError::Compatible)?;
+        let num_atoms: usize = species.iter().map(|(_, count)| count).sum();
+        let mut frac_coords = Vec::with_capacity(num_atoms);
+        let mut flags = Vec::with_capacity(num_atoms);
+        for _ in 0..num_atoms {
+            let line = next_line()?;
+            let coord = Point3::from(parse_vector(line)?);
+            frac_coords.push(if cartesian {
+                inverse_bases * (coord * scale)
+            } else {
+                coord
+            });
+            let flag: Vec<bool> = line
+                .split_whitespace()
+                .skip(3)
+                .take(3)
+                .map(|v| v.starts_with(['T', 't']))
+                .collect();
+            flags.push(match flag.as_slice() {
+                &[x, y, z] => [x, y, z],
+                _ => [true; 3],
+            });
+        }
+        Ok(Self {
+            comment,
+            lattice_bases,
+            species,
+            frac_coords,
+            selective_dynamics: selective.then_some(flags),
+        })
+    }
+
+    /// Write the model of another format, grouping the consecutive atoms of
+    /// the same element.
+    pub fn from_model<T: CrystalModel>(model: &T, comment: &str) -> Self {
+        let lattice_bases = model.get_cell_parameters().lattice_bases();
+        let inverse_bases = lattice_bases
+            .try_inverse()
+            .expect("Matrix is not invertible");
+        let frac_coords = model
+            .get_atom_data()
+            .coords_repr()
+            .iter()
+            .map(|cd| match cd {
+                CoordData::Fractional(frac) => *frac,
+                CoordData::Cartesian(cart) => inverse_bases * cart,
+            })
+            .collect();
+        let mut poscar = Self {
+            comment: comment.to_string(),
+            lattice_bases,
+            species: Vec::new(),
+            frac_coords,
+            selective_dynamics: None,
+        };
+        model
+            .get_atom_data()
+            .symbols_repr()
+            .iter()
+            .for_each(|&symbol| poscar.push_species(symbol));
+        poscar
+    }
+
+    /// The model with the new atoms, in fractional coordinates, appended as
+    /// new groups of the species lines. The new atoms are free to move.
+    pub fn with_new_atoms(&self, new_atoms: &[(ElementSymbol, Point3<f64>)]) -> Self {
+        let mut poscar = self.clone();
+        let num_groups = poscar.species.len();
+        new_atoms.iter().for_each(|&(symbol, frac_coord)| {
+            let name = symbol_name(symbol);
+            let appended = poscar.species.len() > num_groups;
+            match poscar.species.last_mut() {
+                Some((last, count)) if appended && *last == name => *count += 1,
+                _ => poscar.species.push((name, 1)),
+            }
+            poscar.frac_coords.push(frac_coord);
+            if let Some(flags) = poscar.selective_dynamics.as_mut() {
+                flags.push([true; 3]);
+            }
+        });
+        poscar
+    }
+
+    /// The model with selective dynamics on, and the atoms of the indices
+    /// fixed. Unchanged when no atom is fixed.
+    pub fn with_fixed_atoms(&self, fixed: &[usize]) -> Self {
+        let mut poscar = self.clone();
+        if fixed.is_empty() {
+            return poscar;
+        }
+        let mut flags = poscar
+            .selective_dynamics
+            .unwrap_or_else(|| vec![[true; 3]; poscar.frac_coords.len()]);
+        fixed
+            .iter()
+            .filter(|&&i| i < flags.len())
+            .for_each(|&i| flags[i] = [false; 3]);
+        poscar.selective_dynamics = Some(flags);
+        poscar
+    }
+
+    /// Elements of the groups in the species lines, in order, e.g. for the `POTCAR`.
+    pub fn species_names(&self) -> Vec<&str> {
+        self.species.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    pub fn num_atoms(&self) -> usize {
+        self.frac_coords.len()
+    }
+
+    /// The `.cell` model of the same lattice and atoms, for the search.
+    pub fn to_cell_document(&self) -> Result<CellDocument, FormatError> {
+        let atoms = self
+            .species
+            .iter()
+            .flat_map(|(name, count)| std::iter::repeat(name).take(*count))
+            .zip(self.frac_coords.iter())
+            .map(|(name, frac)| {
+                let element = name
+                    .parse::<Element>()
+                    .map_err(|_| FormatError::Compatible)?;
+                Ok((element.symbol(), *frac))
+            })
+            .collect::<Result<Vec<(ElementSymbol, Point3<f64>)>, FormatError>>()?;
+        Ok(new_cell_document(&self.lattice_bases, &atoms))
+    }
+
+    fn push_species(&mut self, symbol: ElementSymbol) {
+        let name = symbol_name(symbol);
+        match self.species.last_mut() {
+            Some((last, count)) if *last == name => *count += 1,
+            _ => self.species.push((name, 1)),
+        }
+    }
+}
+
+impl Display for Poscar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.comment)?;
+        writeln!(f, "{:19.14}", 1.0)?;
+        self.lattice_bases
+            .column_iter()
+            .try_for_each(|v| writeln!(f, " {:22.16}{:22.16}{:22.16}", v[0], v[1], v[2]))?;
+        let names: String = self
+            .species
+            .iter()
+            .map(|(name, _)| format!("{:>5}", name))
+            .collect();
+        let counts: String = self
+            .species
+            .iter()
+            .map(|(_, count)| format!("{:>5}", count))
+            .collect();
+        writeln!(f, "{}", names)?;
+        writeln!(f, "{}", counts)?;
+        if self.selective_dynamics.is_some() {
+            writeln!(f, "Selective dynamics")?;
+        }
+        writeln!(f, "Direct")?;
+        self.frac_coords.iter().enumerate().try_for_each(|(i, p)| {
+            write!(f, " {:20.16}{:20.16}{:20.16}", p.x, p.y, p.z)?;
+            if let Some(flags) = self.selective_dynamics.as_ref() {
+                flags[i]
+                    .iter()
+                    .try_for_each(|&free| write!(f, "   {}", if free { "T" } else { "F" }))?;
+            }
+            writeln!(f)
+        })
+    }
+}
+
+fn parse_vector(line: &str) -> Result<Vector3<f64>, FormatError> {
+    let xyz: Vec<f64> = line
+        .split_whitespace()
+        .take(3)
+        .map(|v| v.parse::<f64>().map_err(|_| FormatError::Compatible))
+        .collect::<Result<Vec<f64>, FormatError>>()?;
+    if xyz.len() < 3 {
+        return Err(FormatError::Compatible);
+    }
+    Ok(Vector3::new(xyz[0], xyz[1], xyz[2]))
+}
+
+/// The element of a species name, e.g. `Pt` of `Pt_pv` or `Pt/a3b5c7`.
+fn element_name(name: &str) -> String {
+    name.split(['_', '/']).next().unwrap_or(name).to_string()
+}
+
+fn symbol_name(symbol: ElementSymbol) -> String {
+    ELEMENT_TABLE.get_by_symbol(symbol).symbol_to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use castep_periodic_table::element::ElementSymbol;
+    use chemrust_core::data::{atom::CoreAtomData, lattice::CrystalModel};
+    use nalgebra::Point3;
+
+    use super::Poscar;
+
+    #[test]
+    fn selective_dynamics_kept() {
+        let content = "Pt(111) slab
+1.0
+  5.54  0.00  0.00
+ -2.77  4.80  0.00
+  0.00  0.00 20.00
+Pt_pv
+2
+Selective dynamics
+Direct
+ 0.0 0.0 0.10 F F F
+ 0.333333 0.666667 0.21 T T T
+";
+        let poscar = Poscar::parse(content).unwrap();
+        assert_eq!(poscar.species_names(), vec!["Pt"]);
+        let with_o = poscar.with_new_atoms(&[(ElementSymbol::O, Point3::new(0.0, 0.0, 0.3))]);
+        assert_eq!(with_o.species_names(), vec!["Pt", "O"]);
+        let written = with_o.to_string();
+        assert!(written.contains("Selective dynamics"));
+        let reread = Poscar::parse(&written).unwrap();
+        assert_eq!(reread.selective_dynamics.unwrap()[0], [false; 3]);
+        let cell = with_o.to_cell_document().unwrap();
+        assert_eq!(
+            cell.get_atom_data().symbols_repr(),
+            &[ElementSymbol::Pt, ElementSymbol::Pt, ElementSymbol::O]
+        );
+        assert!(cell.other_entries().is_none());
+    }
+}
@@ -1,17 +1,25 @@
 use std::{error::Error, path::Path};
 
+use castep_periodic_table::{
+    data::ELEMENT_TABLE,
+    element::{ElementSymbol, LookupElement},
+};
 use chemrust_core::data::{
     atom::CoreAtomData,
     geom::coordinates::CoordData,
     lattice::{CrystalModel, UnitCellParameters},
 };
-use nalgebra::Point3;
+use chemrust_nasl::{SlabAnalysis, SymmetryAnalysis};
+use nalgebra::{Matrix3, Point3, Unit, Vector3};
 
-use crate::supportive_data::FractionalCoordRange;
+use crate::{
+    supportive_data::{FractionalCoordRange, KeepSide},
+    yaml_parser::TaskTable,
+};
 
 use super::{
     format_identify::{self, match_format, ModelFormat},
-    format_loader::load_cell_file,
+    format_loader::{load_cell_file, load_cif_file, load_model_file},
 };
 
 pub fn boundary_check(v: f64) -> f64 {
@@ -29,6 +37,10 @@ pub fn load_model<P: AsRef<Path>>(model_path: &P) -> Result<ModelFormat, Box<dyn
 
     match format {
         format_identify::AcceptFormat::Cell => Ok(ModelFormat::Cell(load_cell_file(model_path)?)),
+        format_identify::AcceptFormat::Cif => Ok(ModelFormat::Cell(load_cif_file(model_path)?)),
+        format_identify::AcceptFormat::Poscar => {
+            Ok(ModelFormat::Cell(load_model_file(model_path)?))
+        }
     }
 }
 
@@ -77,3 +89,140 @@ pub fn get_to_check_atom<T: CrystalModel>(
         })
         .collect()
 }
+
+/// Mass-weighted centre of the atoms, with their symbols in the same order.
+pub fn centre_of_mass(points: &[Point3<f64>], symbols: &[ElementSymbol]) -> Point3<f64> {
+    let (weighted_sum, total_mass) = points.iter().zip(symbols.iter()).fold(
+        (Vector3::zeros(), 0.0),
+        |(sum, total), (p, &symbol)| {
+            let mass = ELEMENT_TABLE.get_by_symbol(symbol).mass();
+            (sum + p.coords * mass, total + mass)
+        },
+    );
+    Point3::from(weighted_sum / total_mass)
+}
+
+/// Monkhorst-Pack grid along the lattice vectors with the spacing (Å⁻¹,
+/// without the factor of 2π) of the k-points.
+pub fn mp_grid(lattice_bases: &Matrix3<f64>, spacing: f64) -> [u32; 3] {
+    let inverse_bases = lattice_bases
+        .try_inverse()
+        .expect("Matrix is not invertible");
+    let mut grid = [1_u32; 3];
+    inverse_bases
+        .row_iter()
+        .zip(grid.iter_mut())
+        .for_each(|(reciprocal, n)| *n = ((reciprocal.norm() / spacing).ceil() as u32).max(1));
+    grid
+}
+
+/// Monkhorst-Pack grid of the model by the k-point spacing of the task, with
+/// one k-point along the vacuum of slabs and the non-periodic directions.
+pub fn detect_kpoint_grid<T: CrystalModel>(model: &T, task_config: &TaskTable) -> [u32; 3] {
+    let lattice_bases = model.get_cell_parameters().lattice_bases();
+    let mut grid = mp_grid(&lattice_bases, task_config.kpoint_spacing());
+    if let Some(slab) = detect_slab(model) {
+        grid[slab.vacuum_axis()] = 1;
+    }
+    (0..3)
+        .filter(|&axis| !task_config.periodicity().is_periodic(axis))
+        .for_each(|axis| grid[axis] = 1);
+    grid
+}
+
+/// Gaps along the lattice vectors at least this wide (Å) are taken as vacuum.
+pub(super) const MIN_VACUUM_GAP: f64 = 5.0;
+
+/// The vacuum and the surface normal of the model when it is a slab.
+pub fn detect_slab<T: CrystalModel>(model: &T) -> Option<SlabAnalysis> {
+    let all_range = FractionalCoordRange::new(0.0, 1.0);
+    let points: Vec<Point3<f64>> = get_to_check_atom(model, all_range, all_range, all_range)
+        .iter()
+        .map(|(_i, point)| *point)
+        .collect();
+    SlabAnalysis::new(
+        &points,
+        model.get_cell_parameters().lattice_bases(),
+        MIN_VACUUM_GAP,
+    )
+}
+
+/// Symmetry operations of the model for grouping the equivalent sites. Unless
+/// both sides are kept, the operations keep the surface normal of slabs, so
+/// the sites on the two faces are told apart.
+pub fn detect_symmetry<T: CrystalModel>(
+    model: &T,
+    task_config: &TaskTable,
+    tolerance: f64,
+) -> SymmetryAnalysis {
+    let all_range = FractionalCoordRange::new(0.0, 1.0);
+    let points: Vec<Point3<f64>> = get_to_check_atom(model, all_range, all_range, all_range)
+        .iter()
+        .map(|(_i, point)| *point)
+        .collect();
+    let symbols = model.get_atom_data().symbols_repr();
+    let lattice_bases = model.get_cell_parameters().lattice_bases();
+    let surface_normal = match task_config.keep_side() {
+        KeepSide::Both => None,
+        _ => task_config
+            .surface_normal()
+            .map(|n| Unit::new_normalize(Vector3::from(n)))
+            .or_else(|| {
+                SlabAnalysis::new(&points, lattice_bases, MIN_VACUUM_GAP).map(|slab| slab.normal())
+            }),
+    };
+    SymmetryAnalysis::new(
+        &points,
+        &symbols,
+        lattice_bases,
+        task_config.periodicity(),
+        surface_normal,
+        tolerance,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{Matrix3, Vector3};
+
+    use crate::execution::{format_loader::load_cell_content, test_fixtures::task};
+
+    use super::{detect_kpoint_grid, mp_grid};
+
+    #[test]
+    fn grid_by_spacing() {
+        let orthorhombic = Matrix3::from_diagonal(&Vector3::new(5.0, 10.0, 30.0));
+        // 1/5, 1/10 and 1/30 Å⁻¹ over 0.07 Å⁻¹, rounded up
+        assert_eq!(mp_grid(&orthorhombic, 0.07), [3, 2, 1]);
+        assert_eq!(mp_grid(&orthorhombic, 0.045), [5, 3, 1]);
+        // Lattice vectors as columns, `a` and `b` at 120°
+        let hexagonal = Matrix3::new(5.54, -2.77, 0.0, 0.0, 4.80, 0.0, 0.0, 0.0, 25.0);
+        let grid = mp_grid(&hexagonal, 0.05);
+        assert_eq!(grid[0], grid[1]);
+        // 1 / (5.54 sin 120°) = 0.208 Å⁻¹
+        assert_eq!(grid, [5, 5, 1]);
+        // Never below one k-point
+        assert_eq!(mp_grid(&orthorhombic, 1.0), [1, 1, 1]);
+    }
+
+    #[test]
+    fn one_kpoint_along_vacuum() {
+        let slab = load_cell_content(
+            "%BLOCK LATTICE_CART
+5.0 0.0 0.0
+0.0 5.0 0.0
+0.0 0.0 25.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+Pt 0.0 0.0 0.1
+Pt 0.5 0.5 0.18
+%ENDBLOCK POSITIONS_FRAC
+"
+            .to_string(),
+        )
+        .unwrap();
+        // 0.2 Å⁻¹ over 0.07 Å⁻¹ along `a` and `b`, and the vacuum along `c`
+        assert_eq!(detect_kpoint_grid(&slab, &task(false)), [3, 3, 1]);
+    }
+}
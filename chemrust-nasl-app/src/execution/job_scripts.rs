@@ -0,0 +1,191 @@
+use std::fs::read_to_string;
+
+use crate::{
+    error::RunError,
+    supportive_data::{ExportFormat, JobScript, Scheduler},
+};
+
+const SLURM_TEMPLATE: &str = "#!/bin/bash
+#SBATCH --job-name={job_name}
+#SBATCH --nodes=1
+#SBATCH --ntasks={cores}
+#SBATCH --time={walltime}
+
+{command}
+";
+
+const SLURM_ARRAY_TEMPLATE: &str = "#!/bin/bash
+#SBATCH --job-name={job_name}
+#SBATCH --nodes=1
+#SBATCH --ntasks={cores}
+#SBATCH --time={walltime}
+#SBATCH --array=1-{num_seeds}
+
+SEED=$(sed -n \"${SLURM_ARRAY_TASK_ID}p\" {seed_list})
+{command}
+";
+
+const PBS_TEMPLATE: &str = "#!/bin/bash
+#PBS -N {job_name}
+#PBS -l select=1:ncpus={cores}:mpiprocs={cores}
+#PBS -l walltime={walltime}
+
+cd $PBS_O_WORKDIR
+{command}
+";
+
+const PBS_ARRAY_TEMPLATE: &str = "#!/bin/bash
+#PBS -N {job_name}
+#PBS -l select=1:ncpus={cores}:mpiprocs={cores}
+#PBS -l walltime={walltime}
+#PBS -J 1-{num_seeds}
+
+cd $PBS_O_WORKDIR
+SEED=$(sed -n \"${PBS_ARRAY_INDEX}p\" {seed_list})
+{command}
+";
+
+/// Fills the template of the job scripts for the seeds of the export format.
+#[derive(Debug, Clone)]
+pub struct JobScripts {
+    template: String,
+    scheduler: Scheduler,
+    format: ExportFormat,
+    cores: usize,
+    walltime: String,
+    executable: String,
+    array: bool,
+    /// Whether each seed is a directory, entered by the array jobs
+    seed_dirs: bool,
+}
+
+impl JobScripts {
+    /// Reads the `template` of the settings, or takes the built-in one.
+    pub fn new(
+        settings: &JobScript,
+        format: ExportFormat,
+        seed_dirs: bool,
+    ) -> Result<Self, RunError> {
+        let template = match settings.template() {
+            Some(path) => read_to_string(path).map_err(|_| {
+                RunError::Message(format!("Failed to read the job script template {path}"))
+            })?,
+            None => match (settings.scheduler(), settings.array()) {
+                (Scheduler::Slurm, false) => SLURM_TEMPLATE,
+                (Scheduler::Slurm, true) => SLURM_ARRAY_TEMPLATE,
+                (Scheduler::Pbs, false) => PBS_TEMPLATE,
+                (Scheduler::Pbs, true) => PBS_ARRAY_TEMPLATE,
+            }
+            .to_string(),
+        };
+        let executable = settings.executable().unwrap_or(match format {
+            ExportFormat::Castep => "castep.mpi",
+            ExportFormat::Vasp => "vasp_std",
+            ExportFormat::QuantumEspresso => "pw.x",
+        });
+        Ok(Self {
+            template,
+            scheduler: settings.scheduler(),
+            format,
+            cores: settings.cores(),
+            walltime: settings.walltime().to_string(),
+            executable: executable.to_string(),
+            array: settings.array(),
+            seed_dirs,
+        })
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.array
+    }
+
+    /// `slurm` or `pbs`
+    pub fn extension(&self) -> &str {
+        match self.scheduler {
+            Scheduler::Slurm => "slurm",
+            Scheduler::Pbs => "pbs",
+        }
+    }
+
+    /// Script submitted from the directory of the seed.
+    pub fn seed_script(&self, seed: &str) -> String {
+        let command = self.command(seed, false);
+        self.fill(&[("job_name", seed), ("seed", seed), ("command", &command)])
+    }
+
+    /// Array job submitted from the `export_dir`, running the seed on the
+    /// line of the task index in the `seed_list`.
+    pub fn array_script(&self, job_name: &str, seed_list: &str, num_seeds: usize) -> String {
+        let command = self.command("$SEED", true);
+        self.fill(&[
+            ("job_name", job_name),
+            ("seed", "$SEED"),
+            ("command", &command),
+            ("seed_list", seed_list),
+            ("num_seeds", &num_seeds.to_string()),
+        ])
+    }
+
+    /// The directories of the seeds are entered from the `export_dir`.
+    fn command(&self, seed: &str, from_export_dir: bool) -> String {
+        let mpirun = format!("mpirun -np {} {}", self.cores, self.executable);
+        let command = match self.format {
+            ExportFormat::Castep => format!("{mpirun} {seed}"),
+            ExportFormat::Vasp => mpirun,
+            ExportFormat::QuantumEspresso => format!("{mpirun} -in {seed}.pwi > {seed}.pwo"),
+        };
+        if from_export_dir && self.seed_dirs {
+            format!("cd {seed} && {command}")
+        } else {
+            command
+        }
+    }
+
+    fn fill(&self, values: &[(&str, &str)]) -> String {
+        let cores = self.cores.to_string();
+        [
+            ("cores", cores.as_str()),
+            ("walltime", self.walltime.as_str()),
+            ("executable", self.executable.as_str()),
+        ]
+        .iter()
+        .chain(values.iter())
+        .fold(self.template.clone(), |script, (name, value)| {
+            script.replace(&format!("{{{name}}}"), value)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::supportive_data::{ExportFormat, JobScript, Scheduler};
+
+    use super::JobScripts;
+
+    #[test]
+    fn fill_builtin_templates() {
+        let settings = JobScript {
+            scheduler: Scheduler::Slurm,
+            template: None,
+            cores: 64,
+            walltime: "12:00:00".to_string(),
+            executable: None,
+            array: false,
+        };
+        let scripts = JobScripts::new(&settings, ExportFormat::Castep, false).unwrap();
+        let script = scripts.seed_script("Pt_111_hollow_fcc_1_2_3");
+        assert!(script.contains("#SBATCH --ntasks=64"));
+        assert!(script.contains("mpirun -np 64 castep.mpi Pt_111_hollow_fcc_1_2_3"));
+        let settings = JobScript {
+            scheduler: Scheduler::Pbs,
+            array: true,
+            ..settings
+        };
+        let scripts = JobScripts::new(&settings, ExportFormat::Vasp, true).unwrap();
+        let script = scripts.array_script("Pt_111", "Pt_111_seeds.txt", 12);
+        assert!(script.contains("#PBS -l select=1:ncpus=64:mpiprocs=64"));
+        assert!(script.contains("#PBS -J 1-12"));
+        assert!(script.contains("sed -n \"${PBS_ARRAY_INDEX}p\" Pt_111_seeds.txt"));
+        assert!(script.contains("cd $SEED && mpirun -np 64 vasp_std"));
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    fs::{create_dir_all, read_to_string, remove_dir, remove_file, write},
+    io::Error as IoError,
+    path::{Path, PathBuf},
+};
+
+use crate::error::RunError;
+
+/// Lists the files written by the previous runs, relative to the `export_dir`.
+const MANIFEST_FILENAME: &str = ".rhino_manifest";
+
+/// Files written by the export. On a dry run, nothing is written but the
+/// files are still listed, to report what would be written.
+#[derive(Debug, Default)]
+pub struct ExportManifest {
+    dry_run: bool,
+    /// Path and size in bytes of each file
+    files: RefCell<Vec<(PathBuf, usize)>>,
+}
+
+impl ExportManifest {
+    pub fn new(dry_run: bool) -> Self {
+        Self {
+            dry_run,
+            files: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<(), IoError> {
+        if self.dry_run {
+            Ok(())
+        } else {
+            create_dir_all(path)
+        }
+    }
+
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(
+        &self,
+        path: P,
+        contents: C,
+    ) -> Result<(), IoError> {
+        self.files
+            .borrow_mut()
+            .push((path.as_ref().to_path_buf(), contents.as_ref().len()));
+        if self.dry_run {
+            Ok(())
+        } else {
+            write(path, contents)
+        }
+    }
+
+    /// Print the number, names and total size of the files.
+    pub fn report(&self) {
+        let files = self.files.borrow();
+        let total_size: usize = files.iter().map(|(_, size)| size).sum();
+        println!(
+            "Dry run: {} files ({}) would be written:",
+            files.len(),
+            readable_size(total_size)
+        );
+        files
+            .iter()
+            .for_each(|(path, size)| println!("  {} ({})", path.display(), readable_size(*size)));
+    }
+
+    /// Add the written files to the manifest in the `export_dir`.
+    pub fn save(&self, export_dir: &Path) -> Result<(), IoError> {
+        let files: Vec<PathBuf> = self
+            .files
+            .borrow()
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect();
+        record_files(export_dir, &files)
+    }
+}
+
+/// Merge the files into the manifest in the `export_dir`.
+pub fn record_files(export_dir: &Path, files: &[PathBuf]) -> Result<(), IoError> {
+    let mut listed = manifest_entries(export_dir).unwrap_or_default();
+    files.iter().for_each(|path| {
+        let relative = path.strip_prefix(export_dir).unwrap_or(path);
+        listed.insert(relative.to_path_buf());
+    });
+    let content: String = listed
+        .iter()
+        .map(|path| format!("{}\n", path.display()))
+        .collect();
+    write(export_dir.join(MANIFEST_FILENAME), content)
+}
+
+/// Remove the files listed in the manifest of the `export_dir`, the manifest
+/// itself, and the directories left empty by them. Other files are kept.
+/// The files failing to be removed stay listed in the manifest, and are
+/// reported in the error.
+/// # Returns
+/// Number of the removed files.
+pub fn clean_export_dir(export_dir: &Path) -> Result<usize, RunError> {
+    let manifest_path = export_dir.join(MANIFEST_FILENAME);
+    let listed = manifest_entries(export_dir).ok_or_else(|| {
+        RunError::Message(format!(
+            "No manifest of a previous run is found in {}",
+            export_dir.display()
+        ))
+    })?;
+    let mut num_removed = 0_usize;
+    let mut failed: Vec<(PathBuf, IoError)> = Vec::new();
+    let mut dirs: BTreeSet<PathBuf> = BTreeSet::new();
+    listed.iter().for_each(|relative| {
+        let path = export_dir.join(relative);
+        if path.is_file() {
+            match remove_file(&path) {
+                Ok(()) => num_removed += 1,
+                Err(e) => failed.push((relative.clone(), e)),
+            }
+        }
+        relative
+            .ancestors()
+            .skip(1)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .for_each(|dir| {
+                dirs.insert(export_dir.join(dir));
+            });
+    });
+    // Deeper directories first; `remove_dir` fails on those still holding files.
+    dirs.iter().rev().for_each(|dir| {
+        let _ = remove_dir(dir);
+    });
+    if failed.is_empty() {
+        remove_file(manifest_path).map_err(RunError::IO)?;
+        return Ok(num_removed);
+    }
+    let content: String = failed
+        .iter()
+        .map(|(path, _)| format!("{}\n", path.display()))
+        .collect();
+    write(manifest_path, content).map_err(RunError::IO)?;
+    let failures: Vec<String> = failed
+        .iter()
+        .map(|(path, e)| format!("  {}: {e}", path.display()))
+        .collect();
+    Err(RunError::Message(format!(
+        "{num_removed} files are removed, but {} files failed to be removed, and are kept in the manifest:\n{}",
+        failed.len(),
+        failures.join("\n")
+    )))
+}
+
+/// Files listed in the manifest of the `export_dir`, relative to it, or
+/// `None` without a manifest.
+pub fn manifest_entries(export_dir: &Path) -> Option<BTreeSet<PathBuf>> {
+    read_to_string(export_dir.join(MANIFEST_FILENAME))
+        .ok()
+        .map(|content| {
+            content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(PathBuf::from)
+                .collect()
+        })
+}
+
+fn readable_size(size: usize) -> String {
+    match size {
+        0..=1023 => format!("{size} B"),
+        1024..=1048575 => format!("{:.1} KiB", size as f64 / 1024.0),
+        _ => format!("{:.1} MiB", size as f64 / 1048576.0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs::{create_dir_all, remove_dir_all, write},
+        path::PathBuf,
+    };
+
+    use super::{clean_export_dir, record_files, MANIFEST_FILENAME};
+
+    #[test]
+    fn clean_listed_files_only() {
+        let export_dir =
+            std::env::temp_dir().join(format!("rhino_clean_export_{}", std::process::id()));
+        let _ = remove_dir_all(&export_dir);
+        create_dir_all(export_dir.join("Pt_CO_1")).unwrap();
+        create_dir_all(export_dir.join("Pt_CO_2")).unwrap();
+        let listed: Vec<PathBuf> = [
+            "Pt_CO_1/Pt_CO_1.cell",
+            "Pt_CO_2/Pt_CO_2.cell",
+            "Pt_CO_all.cell",
+        ]
+        .iter()
+        .map(|path| export_dir.join(path))
+        .collect();
+        listed
+            .iter()
+            .for_each(|path| write(path, "listed").unwrap());
+        // Listed but already removed by the user
+        let removed = export_dir.join("Pt_CO_3.cell");
+        record_files(&export_dir, &[listed.clone(), vec![removed]].concat()).unwrap();
+        let unlisted = [
+            export_dir.join("notes.txt"),
+            export_dir.join("Pt_CO_2/Pt_CO_2.castep"),
+        ];
+        unlisted
+            .iter()
+            .for_each(|path| write(path, "unlisted").unwrap());
+        assert_eq!(clean_export_dir(&export_dir).unwrap(), 3);
+        assert!(listed.iter().all(|path| !path.exists()));
+        assert!(unlisted.iter().all(|path| path.exists()));
+        assert!(!export_dir.join(MANIFEST_FILENAME).exists());
+        // Left empty by the listed files, unlike the one still holding a file
+        assert!(!export_dir.join("Pt_CO_1").exists());
+        assert!(export_dir.join("Pt_CO_2").exists());
+        // Nothing to clean without a manifest
+        assert!(clean_export_dir(&export_dir).is_err());
+        assert!(unlisted.iter().all(|path| path.exists()));
+        remove_dir_all(&export_dir).unwrap();
+    }
+}
@@ -1,81 +1,275 @@
-use chemrust_core::data::lattice::CrystalModel;
-use std::fs::read_to_string;
-
-use castep_cell_io::CellParser;
-use chemrust_nasl::{search_sites, SearchConfig, SearchReports, SiteIndex};
-use nalgebra::Point3;
+use castep_cell_io::CellDocument;
+use chemrust_core::data::{
+    atom::CoreAtomData,
+    lattice::{CrystalModel, UnitCellParameters},
+};
+use chemrust_nasl::{
+    search_sites, IntersectionSide, PeriodicImages, SearchConfig, SearchReports, SiteIndex,
+};
+use nalgebra::{Point3, Unit, Vector3};
 
 use crate::{
-    error::{FormatError, RunError},
-    supportive_data::FractionalCoordRange,
+    element_data::default_exclusion_radius,
+    error::RunError,
+    interactive_ui::RunMode,
+    supportive_data::{ExportFormat, ExportLayout, FractionalCoordRange, KeepSide},
     yaml_parser::TaskTable,
 };
 
+pub use self::{manifest::clean_export_dir, potentials::copy_potentials};
+
 use self::{
-    export::export_all, format_identify::match_format, format_loader::load_cell_file,
-    helpers::get_to_check_atom,
+    constraints::FixedAtoms,
+    export::{export_adsorbate_all, export_all, write_array_job, BaseModel},
+    format_identify::{match_format, AcceptFormat},
+    format_loader::{load_adsorbate, load_model_file, load_poscar_file},
+    helpers::{
+        centre_of_mass, detect_kpoint_grid, detect_slab, detect_symmetry, get_to_check_atom,
+    },
+    job_scripts::JobScripts,
+    manifest::ExportManifest,
+    potentials::seed_potential_files,
 };
 
+mod castep_inputs;
+mod cell_editor;
+mod constraints;
 mod export;
+mod extxyz;
 mod format_identify;
 mod format_loader;
 mod helpers;
+mod job_scripts;
+mod manifest;
+mod potentials;
+mod qe_inputs;
+#[cfg(test)]
+mod test_fixtures;
+mod vasp_inputs;
 
-pub fn search_with_length<T: CrystalModel>(
+/// Build the `SiteIndex` and `SearchConfig` of the model by the task, and run `f` with them.
+fn with_search_setup<T: CrystalModel, R>(
     model: &T,
-    bondlength: f64,
-    x_range: FractionalCoordRange,
-    y_range: FractionalCoordRange,
-    z_range: FractionalCoordRange,
-) -> Result<SearchReports, RunError> {
-    let to_check = get_to_check_atom(model, x_range, y_range, z_range);
+    task_config: &TaskTable,
+    f: impl FnOnce(&SiteIndex, &SearchConfig) -> R,
+) -> Result<R, RunError> {
+    let new_element = task_config.new_element().symbol();
+    let symbols = model.get_atom_data().symbols_repr();
+    let atom_bondlengths: Vec<f64> = symbols
+        .iter()
+        .map(|&symbol| {
+            task_config.bondlength_for(symbol).ok_or_else(|| {
+                RunError::Message(format!(
+                    "No target bondlength is given for the element {:?} in the model.",
+                    symbol
+                ))
+            })
+        })
+        .collect::<Result<Vec<f64>, RunError>>()?;
+    let bondlength = atom_bondlengths.iter().cloned().fold(0.0, f64::max);
+    let to_check = get_to_check_atom(
+        model,
+        task_config.x_range(),
+        task_config.y_range(),
+        task_config.z_range(),
+    );
     let all_range = FractionalCoordRange::new(0.0, 1.0);
     let all_points: Vec<Point3<f64>> = get_to_check_atom(model, all_range, all_range, all_range)
         .iter()
         .map(|(_i, point)| *point)
         .collect();
-    let site_index = SiteIndex::new(&all_points);
-    let search_config = SearchConfig::new(&to_check, bondlength);
-    let search_report = search_sites(&site_index, &search_config);
-    if search_report.viable_single_points().is_none()
-        && search_report.viable_double_points().is_none()
-        && search_report.points().is_none()
+    let search_config =
+        SearchConfig::new(&to_check, bondlength).with_atom_bondlengths(&atom_bondlengths);
+    let search_config = match task_config
+        .target_bondlength()
+        .and_then(|target| target.range())
     {
-        Err(RunError::Message(
-            "No available results for this config.".to_string(),
-        ))
-    } else {
-        Ok(search_report)
-    }
+        Some((min, max)) => search_config.with_bondlength_range(min, max),
+        None => search_config,
+    };
+    let exclusion_radii: Vec<f64> = symbols
+        .iter()
+        .enumerate()
+        .map(|(i, &symbol)| {
+            task_config.exclusion_radius(symbol).unwrap_or_else(|| {
+                default_exclusion_radius(new_element, symbol)
+                    .min(search_config.bondlength_range_of(i).0)
+            })
+        })
+        .collect();
+    let lattice_bases = model.get_cell_parameters().lattice_bases();
+    let surface_normal = task_config
+        .surface_normal()
+        .map(|n| Unit::new_normalize(Vector3::from(n)))
+        .unwrap_or_else(|| detect_slab(model).map_or_else(Vector3::z_axis, |slab| slab.normal()));
+    let intersection_side = match task_config.keep_side() {
+        KeepSide::Both => IntersectionSide::Both,
+        KeepSide::Normal => IntersectionSide::Normal(surface_normal),
+        KeepSide::AwayFromCentre => {
+            IntersectionSide::AwayFrom(centre_of_mass(&all_points, &symbols))
+        }
+    };
+    let search_config = search_config
+        .with_exclusion_radii(&exclusion_radii)
+        .with_intersection_side(intersection_side)
+        .with_surface_normal(surface_normal);
+    let images = PeriodicImages::new(
+        &all_points,
+        lattice_bases,
+        task_config.periodicity(),
+        2.0 * search_config
+            .max_distance()
+            .max(search_config.max_exclusion_radius()),
+    );
+    let site_index = SiteIndex::new_periodic(&images);
+    Ok(f(&site_index, &search_config))
 }
 
-pub fn search(task_config: &TaskTable) -> Result<SearchReports, RunError> {
-    // let ModelFormat::Cell(model) = load_model(&task_config.model_path())?;
-    let format = match_format(&task_config.model_path()).map_err(RunError::FormatError)?;
-    let search_report = match format {
-        format_identify::AcceptFormat::Cell => search_with_length(
-            &load_cell_file(task_config.model_path()).map_err(RunError::FormatError)?,
-            task_config.target_bondlength(),
-            task_config.x_range(),
-            task_config.y_range(),
-            task_config.z_range(),
-        )?,
-    };
-    Ok(search_report)
+/// Search the sites in the model of the task and export them, with the
+/// periodic images and the `SiteIndex` built once for both.
+/// # Returns
+/// Numbers of the exported multiple, single and double sites.
+pub fn search_and_export(task_config: &TaskTable) -> Result<(usize, usize, usize), RunError> {
+    let cell = load_model_file(task_config.model_path()).map_err(RunError::FormatError)?;
+    if task_config.surface_normal().is_none() {
+        if let Some(slab) = detect_slab(&cell) {
+            let normal = slab.normal();
+            println!(
+                "Vacuum of {:.2} Å found along lattice vector {}, surface normal: ({:.3}, {:.3}, {:.3})",
+                slab.vacuum_gap(),
+                ["a", "b", "c"][slab.vacuum_axis()],
+                normal.x,
+                normal.y,
+                normal.z
+            );
+        }
+    }
+    with_search_setup(&cell, task_config, |site_index, search_config| {
+        let search_report = search_sites(site_index, search_config);
+        if search_report.viable_single_points().is_none()
+            && search_report.viable_double_points().is_none()
+            && search_report.points().is_none()
+        {
+            return Err(RunError::Message(
+                "No available results for this config.".to_string(),
+            ));
+        }
+        export_results_in_cell(
+            task_config,
+            cell.clone(),
+            &search_report,
+            site_index,
+            search_config,
+        )
+    })?
 }
 
-pub fn export_results_in_cell(
+fn export_results_in_cell(
     task_config: &TaskTable,
+    cell: CellDocument,
     search_results: &SearchReports,
+    site_index: &SiteIndex,
+    search_config: &SearchConfig,
 ) -> Result<(usize, usize, usize), RunError> {
-    let content = read_to_string(&task_config.model_path)
-        .map_err(|_| RunError::FormatError(FormatError::ReadToString))?;
-    let base_model = CellParser::from(&content)
-        .parse()
-        .map_err(|_| RunError::FormatError(FormatError::Compatible))?;
-    let cell = load_cell_file(&task_config.model_path).map_err(RunError::FormatError)?;
-    let cell_param = cell.get_cell_parameters();
-    let (mul, single, double) = export_all(&base_model, cell_param, task_config, search_results)
-        .map_err(|_| RunError::IO)?;
+    let model_format = match_format(&task_config.model_path).map_err(RunError::FormatError)?;
+    let poscar = match model_format {
+        AcceptFormat::Poscar => {
+            Some(load_poscar_file(&task_config.model_path).map_err(RunError::FormatError)?)
+        }
+        _ => None,
+    };
+    let export_format = task_config.export_format().unwrap_or(match model_format {
+        AcceptFormat::Poscar => ExportFormat::Vasp,
+        _ => ExportFormat::Castep,
+    });
+    let kpoint_grid = detect_kpoint_grid(&cell, task_config);
+    println!(
+        "K-point grid: {} x {} x {} (spacing {} Å⁻¹)",
+        kpoint_grid[0],
+        kpoint_grid[1],
+        kpoint_grid[2],
+        task_config.kpoint_spacing()
+    );
+    let manifest = ExportManifest::new(task_config.run_mode() == RunMode::Dryrun);
+    let fixed_atoms = FixedAtoms::new(&cell, task_config);
+    if task_config
+        .fix_atoms()
+        .is_some_and(|fix_atoms| !fix_atoms.except_site())
+    {
+        println!("{} atoms of the model are fixed", fixed_atoms.num_fixed());
+    }
+    let job_scripts = task_config
+        .job_script()
+        .map(|settings| {
+            let seed_dirs = export_format == ExportFormat::Vasp
+                || task_config.export_layout() == ExportLayout::PerSeed;
+            JobScripts::new(settings, export_format, seed_dirs)
+        })
+        .transpose()?;
+    let base_model = BaseModel::new(
+        cell,
+        export_format,
+        poscar,
+        kpoint_grid,
+        fixed_atoms,
+        manifest,
+        job_scripts,
+    )
+    .with_potential_files(seed_potential_files(task_config)?);
+    let model = base_model.cell();
+    let cell_param = model.get_cell_parameters();
+    let reduced = task_config.symmetry_tolerance().map(|tolerance| {
+        let symmetry = detect_symmetry(model, task_config, tolerance);
+        println!(
+            "Found {} symmetry operations (point group order {}) within {} Å",
+            symmetry.operations().len(),
+            symmetry.point_group_order(),
+            tolerance
+        );
+        search_results.reduced_by_symmetry(&symmetry)
+    });
+    let search_results = reduced.as_ref().unwrap_or(search_results);
+    let clustered = task_config.fingerprint().map(|fingerprint| {
+        search_results.clustered_by_fingerprint(
+            &model.get_atom_data().symbols_repr(),
+            site_index,
+            fingerprint.cutoff(),
+            fingerprint.threshold(),
+        )
+    });
+    let search_results = clustered.as_ref().unwrap_or(search_results);
+    let (mul, single, double) = match task_config.adsorbate() {
+        None => export_all(&base_model, cell_param, task_config, search_results)
+            .map_err(RunError::IO)?,
+        Some(spec) => {
+            let adsorbate = load_adsorbate(spec)?;
+            if adsorbate.anchor_symbol() != task_config.new_element().symbol() {
+                return Err(RunError::Message(format!(
+                    "The anchor atom of the adsorbate is {:?}, but the sites are searched for {:?}",
+                    adsorbate.anchor_symbol(),
+                    task_config.new_element().symbol()
+                )));
+            }
+            export_adsorbate_all(
+                &base_model,
+                cell_param,
+                task_config,
+                search_results,
+                &adsorbate,
+                site_index,
+                search_config,
+            )
+            .map_err(RunError::IO)?
+        }
+    };
+    write_array_job(&base_model, task_config).map_err(RunError::IO)?;
+    let manifest = base_model.manifest();
+    if manifest.is_dry_run() {
+        manifest.report();
+    } else if mul + single + double > 0 {
+        manifest
+            .save(task_config.export_dir())
+            .map_err(RunError::IO)?;
+    }
     Ok((mul, single, double))
 }
@@ -0,0 +1,521 @@
+use std::{
+    fs::{copy, hard_link, read_dir, read_to_string, write},
+    path::{Path, PathBuf},
+};
+
+use castep_cell_io::CellDocument;
+use castep_periodic_table::{data::ELEMENT_TABLE, element::LookupElement};
+
+use crate::{error::RunError, interactive_ui::RunMode, yaml_parser::TaskTable};
+
+use super::{
+    castep_inputs::set_species_pot,
+    cell_editor::{block_rows, find_block, rendered_entries},
+    format_loader::load_cell_file,
+    manifest::{manifest_entries, record_files},
+};
+
+const POTENTIAL_EXTENSIONS: [&str; 3] = ["usp", "recpot", "uspso"];
+
+const PSEUDO_EXTENSIONS: [&str; 1] = ["upf"];
+
+/// An exported seed listed in the manifest, by the way it names its potentials.
+#[derive(Debug, Clone, PartialEq)]
+enum Seed {
+    /// `.cell`, by its `SPECIES_POT`
+    Castep(PathBuf),
+    /// `.pwi`, by the `ATOMIC_SPECIES`, read from its directory
+    QuantumEspresso(PathBuf),
+    /// `POTCAR.spec`, listing the species of the `POSCAR` beside it
+    Vasp(PathBuf),
+}
+
+/// A file to write into the directory of a seed from the potentials.
+#[derive(Debug)]
+struct PotentialCopy {
+    destination: PathBuf,
+    /// Joined in order when more than one, e.g. into a `POTCAR`
+    sources: Vec<PathBuf>,
+}
+
+/// Copy the potentials of every seed listed in the manifest of the
+/// `export_dir` from the `potential_dir` to the directory of the seed,
+/// hard-linked when possible: the potentials named by the `SPECIES_POT` of
+/// the `.cell` seeds, written first by the potentials found for the elements
+/// it does not name, e.g. after a run without `potential_dir`, the
+/// pseudopotentials of the `ATOMIC_SPECIES` of the
+/// `.pwi` seeds, found by the element, and the `POTCAR` of the VASP seeds,
+/// joined from `<potential_dir>/<species>/POTCAR` by the `POTCAR.spec`.
+/// Nothing is written if any element lacks a potential. The copies are added
+/// to the manifest.
+/// # Returns
+/// Number of the seeds.
+pub fn copy_potentials(task_config: &TaskTable) -> Result<usize, RunError> {
+    let potential_dir = task_config.potential_dir().ok_or_else(|| {
+        RunError::Message("`potential_dir` is required to copy the potentials".to_string())
+    })?;
+    let export_dir = task_config.export_dir();
+    let seeds = listed_seeds(export_dir)?;
+    let potential_dir = Path::new(potential_dir);
+    let mut missing: Vec<String> = Vec::new();
+    let mut species_pots: Vec<(PathBuf, String)> = Vec::new();
+    let copies = seeds
+        .iter()
+        .map(|seed| potential_copies(seed, potential_dir, &mut missing, &mut species_pots))
+        .collect::<Result<Vec<Vec<PotentialCopy>>, RunError>>()?;
+    if !missing.is_empty() {
+        return Err(RunError::Message(format!(
+            "No potential is found in {} for: {}",
+            potential_dir.display(),
+            missing.join(", ")
+        )));
+    }
+    species_pots
+        .iter()
+        .try_for_each(|(cell_path, species_pot)| {
+            let content = read_to_string(cell_path)?;
+            write(cell_path, with_species_pot(&content, species_pot))
+        })
+        .map_err(RunError::IO)?;
+    let mut copied: Vec<PathBuf> = Vec::new();
+    copies
+        .iter()
+        .flatten()
+        .try_for_each(|potential_copy| {
+            let PotentialCopy {
+                destination,
+                sources,
+            } = potential_copy;
+            if destination.exists() {
+                return Ok(());
+            }
+            match sources.as_slice() {
+                [source] => {
+                    if hard_link(source, destination).is_err() {
+                        copy(source, destination)?;
+                    }
+                }
+                _ => {
+                    let content = sources
+                        .iter()
+                        .map(read_to_string)
+                        .collect::<Result<String, std::io::Error>>()?;
+                    write(destination, content)?;
+                }
+            }
+            copied.push(destination.clone());
+            Ok(())
+        })
+        .map_err(RunError::IO)?;
+    record_files(export_dir, &copied).map_err(RunError::IO)?;
+    Ok(seeds.len())
+}
+
+/// Potential files of the `potential_dir`, for the `SPECIES_POT` of the
+/// exported `.cell` seeds to name. Empty without a `potential_dir`, or when
+/// it cannot be read other than for a `Full` run.
+pub fn seed_potential_files(task_config: &TaskTable) -> Result<Vec<PathBuf>, RunError> {
+    match task_config.potential_dir() {
+        Some(dir) if task_config.run_mode() == RunMode::Full => {
+            potential_files(Path::new(dir), &POTENTIAL_EXTENSIONS)
+        }
+        Some(dir) => Ok(potential_files(Path::new(dir), &POTENTIAL_EXTENSIONS).unwrap_or_default()),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The seeds in the manifest of the `export_dir`, leaving out the
+/// `_all.cell` overviews and any other file.
+fn listed_seeds(export_dir: &Path) -> Result<Vec<Seed>, RunError> {
+    let listed = manifest_entries(export_dir).ok_or_else(|| {
+        RunError::Message(format!(
+            "No manifest of a previous run is found in {}",
+            export_dir.display()
+        ))
+    })?;
+    Ok(listed
+        .iter()
+        .map(|relative| export_dir.join(relative))
+        .filter_map(|path| {
+            let filename = path.file_name()?.to_str()?;
+            match path.extension().and_then(|ext| ext.to_str()) {
+                _ if filename == "POTCAR.spec" => Some(Seed::Vasp(path)),
+                Some("cell") if !filename.ends_with("_all.cell") => Some(Seed::Castep(path)),
+                Some("pwi") => Some(Seed::QuantumEspresso(path)),
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+/// The potentials to copy for the `seed`, with the elements or species
+/// lacking one added to `missing`. A `.cell` seed whose `SPECIES_POT` does
+/// not name a potential for every element adds the block naming the
+/// potentials found to `species_pots`.
+fn potential_copies(
+    seed: &Seed,
+    potential_dir: &Path,
+    missing: &mut Vec<String>,
+    species_pots: &mut Vec<(PathBuf, String)>,
+) -> Result<Vec<PotentialCopy>, RunError> {
+    let (Seed::Castep(path) | Seed::QuantumEspresso(path) | Seed::Vasp(path)) = seed;
+    let seed_dir = path.parent().unwrap_or(Path::new("."));
+    let mut report_missing = |name: &str| {
+        if !missing.iter().any(|listed| listed == name) {
+            missing.push(name.to_string());
+        }
+    };
+    let copies = match seed {
+        Seed::Castep(cell_path) => {
+            let potential_files = potential_files(potential_dir, &POTENTIAL_EXTENSIONS)?;
+            let mut cell = load_cell_file(cell_path).map_err(RunError::FormatError)?;
+            if species_potentials(&cell)
+                .iter()
+                .any(|(_, filename)| filename.is_none())
+            {
+                set_species_pot(&mut cell, &potential_files);
+                if let Some(block) = find_block(&rendered_entries(&cell), "SPECIES_POT") {
+                    species_pots.push((cell_path.clone(), block.to_string()));
+                }
+            }
+            species_potentials(&cell)
+                .into_iter()
+                .filter_map(|(name, filename)| {
+                    let source = filename.as_deref().and_then(|filename| {
+                        potential_files
+                            .iter()
+                            .find(|path| path.file_name().is_some_and(|f| f == filename))
+                    });
+                    match (source, filename) {
+                        (Some(source), Some(filename)) => Some(PotentialCopy {
+                            destination: seed_dir.join(filename),
+                            sources: vec![source.clone()],
+                        }),
+                        _ => {
+                            report_missing(&name);
+                            None
+                        }
+                    }
+                })
+                .collect()
+        }
+        Seed::QuantumEspresso(pwi_path) => {
+            let pseudo_files = potential_files(potential_dir, &PSEUDO_EXTENSIONS)?;
+            let content = read_to_string(pwi_path).map_err(RunError::IO)?;
+            atomic_species(&content)
+                .into_iter()
+                .filter_map(
+                    |(element, filename)| match find_potential(&pseudo_files, &element) {
+                        Some(source) => Some(PotentialCopy {
+                            destination: seed_dir.join(filename),
+                            sources: vec![source.clone()],
+                        }),
+                        None => {
+                            report_missing(&element);
+                            None
+                        }
+                    },
+                )
+                .collect()
+        }
+        Seed::Vasp(spec_path) => {
+            let content = read_to_string(spec_path).map_err(RunError::IO)?;
+            let sources: Vec<PathBuf> = content
+                .split_whitespace()
+                .map(|species| potential_dir.join(species).join("POTCAR"))
+                .collect();
+            sources
+                .iter()
+                .filter(|source| !source.is_file())
+                .for_each(|source| {
+                    let species = source.parent().and_then(|dir| dir.file_name());
+                    report_missing(&species.unwrap_or_default().to_string_lossy());
+                });
+            vec![PotentialCopy {
+                destination: seed_dir.join("POTCAR"),
+                sources,
+            }]
+        }
+    };
+    Ok(copies)
+}
+
+/// Each element of the `cell` with the potential named for it in the
+/// `SPECIES_POT`, if any.
+fn species_potentials(cell: &CellDocument) -> Vec<(String, Option<String>)> {
+    let entries = rendered_entries(cell);
+    let rows = find_block(&entries, "SPECIES_POT")
+        .map(|block| block_rows(block))
+        .unwrap_or_default();
+    cell.get_elements()
+        .iter()
+        .map(|&symbol| {
+            let name = ELEMENT_TABLE.get_by_symbol(symbol).symbol_to_string();
+            let filename = rows.iter().find_map(|row| {
+                let mut tokens = row.split_whitespace();
+                match tokens.next() {
+                    Some(label) if label.eq_ignore_ascii_case(&name) => {
+                        tokens.next().map(|filename| filename.to_string())
+                    }
+                    _ => None,
+                }
+            });
+            (name, filename)
+        })
+        .collect()
+}
+
+/// The `content` of a `.cell` with the `species_pot` in place of its
+/// `SPECIES_POT` block, if any. Edited as text to keep the per-atom `SPIN`,
+/// the `HUBBARD_U` and the `IONIC_CONSTRAINTS` `castep-cell-io` does not read.
+fn with_species_pot(content: &str, species_pot: &str) -> String {
+    let mut in_block = false;
+    let lines: Vec<&str> = content
+        .lines()
+        .filter(|line| {
+            let mut tokens = line.split_whitespace();
+            let mark = tokens.next().unwrap_or_default();
+            let is_species_pot = tokens
+                .next()
+                .is_some_and(|tag| tag.eq_ignore_ascii_case("SPECIES_POT"));
+            if is_species_pot && mark.eq_ignore_ascii_case("%BLOCK") {
+                in_block = true;
+            }
+            let kept = !in_block;
+            if is_species_pot && mark.eq_ignore_ascii_case("%ENDBLOCK") {
+                in_block = false;
+            }
+            kept
+        })
+        .collect();
+    format!(
+        "{}\n\n{}\n",
+        lines.join("\n").trim_end(),
+        species_pot.trim_end()
+    )
+}
+
+/// The element and the pseudopotential file of each species in the
+/// `ATOMIC_SPECIES` card of a `pw.x` input, the element taken from the
+/// letters of the species label, e.g. `Pt` of `Pt1`.
+fn atomic_species(pw_input: &str) -> Vec<(String, String)> {
+    pw_input
+        .lines()
+        .skip_while(|line| line.trim() != "ATOMIC_SPECIES")
+        .skip(1)
+        .take_while(|line| line.starts_with(char::is_whitespace))
+        .filter_map(|line| {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let [label, _mass, filename] = tokens.as_slice() else {
+                return None;
+            };
+            let element: String = label
+                .chars()
+                .take_while(char::is_ascii_alphabetic)
+                .collect();
+            Some((element, filename.to_string()))
+        })
+        .collect()
+}
+
+/// Potential files in the directory with one of the `extensions`, sorted by name.
+pub fn potential_files(
+    potential_dir: &Path,
+    extensions: &[&str],
+) -> Result<Vec<PathBuf>, RunError> {
+    let mut files: Vec<PathBuf> = read_dir(potential_dir)
+        .map_err(|_| {
+            RunError::Message(format!(
+                "Failed to read the potential directory {}",
+                potential_dir.display()
+            ))
+        })?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// The potential named by the element, e.g. `Pt_00PBE.usp`, `O_00PBE_OP.recpot`
+/// or `Pt.pbe-n-kjpaw_psl.1.0.0.UPF`.
+pub fn find_potential<'a>(potential_files: &'a [PathBuf], element: &str) -> Option<&'a PathBuf> {
+    potential_files.iter().find(|path| {
+        path.file_name()
+            .and_then(|s| s.to_str())
+            .and_then(|name| name.split(['_', '.', '-']).next())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(element))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs::{create_dir_all, read_to_string, remove_dir_all, write},
+        path::{Path, PathBuf},
+    };
+
+    use crate::execution::{
+        manifest::{manifest_entries, record_files},
+        test_fixtures::task_in,
+    };
+
+    use super::{atomic_species, copy_potentials};
+
+    const PT_C_CELL: &str = "%BLOCK LATTICE_CART
+3.0 0.0 0.0
+0.0 3.0 0.0
+0.0 0.0 10.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+Pt 0.0 0.0 0.0
+C 0.0 0.0 0.2
+%ENDBLOCK POSITIONS_FRAC
+
+%BLOCK SPECIES_POT
+Pt Pt_00PBE.usp
+C C_00PBE.usp
+%ENDBLOCK SPECIES_POT
+";
+
+    const PT_C_PWI: &str = "ATOMIC_SPECIES
+  Pt 195.0800 Pt.UPF
+  Pt1 195.0800 Pt.UPF
+  C 12.0110 C.UPF
+CELL_PARAMETERS angstrom
+";
+
+    /// An export of a CASTEP, a QE and a VASP seed, with an unlisted `.cell`
+    /// left in the `export_dir`, and the potentials of Pt and C.
+    fn set_up(name: &str, with_carbon: bool) -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("rhino_{name}_{}", std::process::id()));
+        let _ = remove_dir_all(&root);
+        let export_dir = root.join("export");
+        let potential_dir = root.join("potentials");
+        ["castep", "qe", "vasp", "user"]
+            .iter()
+            .for_each(|dir| create_dir_all(export_dir.join(dir)).unwrap());
+        let seeds = [
+            (export_dir.join("castep/Pt_C_1.cell"), PT_C_CELL),
+            (export_dir.join("castep/Pt_C_all.cell"), PT_C_CELL),
+            (export_dir.join("qe/Pt_C_1.pwi"), PT_C_PWI),
+            (export_dir.join("vasp/POTCAR.spec"), "Pt\nC\n"),
+        ];
+        seeds
+            .iter()
+            .for_each(|(path, content)| write(path, content).unwrap());
+        let listed: Vec<PathBuf> = seeds.into_iter().map(|(path, _)| path).collect();
+        record_files(&export_dir, &listed).unwrap();
+        // Not in the manifest, and naming a potential nowhere to be found
+        write(
+            export_dir.join("user/N.cell"),
+            PT_C_CELL.replace("C 0.0 0.0 0.2", "N 0.0 0.0 0.2"),
+        )
+        .unwrap();
+        let mut elements = vec!["Pt"];
+        if with_carbon {
+            elements.push("C");
+        }
+        elements.iter().for_each(|element| {
+            create_dir_all(potential_dir.join(element)).unwrap();
+            write(
+                potential_dir.join(element).join("POTCAR"),
+                format!("PAW_PBE {element}\n"),
+            )
+            .unwrap();
+            write(
+                potential_dir.join(format!("{element}_00PBE.usp")),
+                format!("{element} usp"),
+            )
+            .unwrap();
+            write(
+                potential_dir.join(format!("{element}.pbe-n-kjpaw_psl.1.0.0.UPF")),
+                format!("{element} upf"),
+            )
+            .unwrap();
+        });
+        (export_dir, potential_dir)
+    }
+
+    #[test]
+    fn copy_potentials_of_listed_seeds() {
+        let (export_dir, potential_dir) = set_up("copy_potentials", true);
+        let num_seeds = copy_potentials(&task_in(&export_dir, Some(&potential_dir))).unwrap();
+        // The `_all.cell` overview and the unlisted `.cell` are left out
+        assert_eq!(num_seeds, 3);
+        let read = |path: &str| read_to_string(export_dir.join(path)).unwrap();
+        assert_eq!(read("castep/Pt_00PBE.usp"), "Pt usp");
+        assert_eq!(read("castep/C_00PBE.usp"), "C usp");
+        // Under the names of the `ATOMIC_SPECIES`, once for both species of Pt
+        assert_eq!(read("qe/Pt.UPF"), "Pt upf");
+        assert_eq!(read("qe/C.UPF"), "C upf");
+        // Joined in the order of the `POTCAR.spec`
+        assert_eq!(read("vasp/POTCAR"), "PAW_PBE Pt\nPAW_PBE C\n");
+        assert!(!export_dir.join("user/N_00PBE.usp").exists());
+        let listed = manifest_entries(&export_dir).unwrap();
+        assert!(listed.contains(Path::new("castep/C_00PBE.usp")));
+        assert!(listed.contains(Path::new("qe/Pt.UPF")));
+        assert!(listed.contains(Path::new("vasp/POTCAR")));
+        remove_dir_all(export_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn species_pot_of_cells_without() {
+        let (export_dir, potential_dir) = set_up("species_pot", true);
+        // Exported without `potential_dir`, with a block `castep-cell-io` does not read
+        let cell_path = export_dir.join("castep/Pt_C_2.cell");
+        let (model, _) = PT_C_CELL.split_once("%BLOCK SPECIES_POT").unwrap();
+        write(
+            &cell_path,
+            format!("{model}%BLOCK HUBBARD_U\neV\n      Pt  d: 2.5000\n%ENDBLOCK HUBBARD_U\n"),
+        )
+        .unwrap();
+        record_files(&export_dir, &[cell_path.clone()]).unwrap();
+        let num_seeds = copy_potentials(&task_in(&export_dir, Some(&potential_dir))).unwrap();
+        assert_eq!(num_seeds, 4);
+        let content = read_to_string(&cell_path).unwrap();
+        assert!(content.contains("%BLOCK SPECIES_POT\n"));
+        assert!(content.contains("      Pt  Pt_00PBE.usp\n"));
+        assert!(content.contains("       C  C_00PBE.usp\n"));
+        assert!(
+            content.contains("%BLOCK HUBBARD_U\neV\n      Pt  d: 2.5000\n%ENDBLOCK HUBBARD_U\n")
+        );
+        // The seeds naming their potentials are left as they are
+        assert_eq!(
+            read_to_string(export_dir.join("castep/Pt_C_1.cell")).unwrap(),
+            PT_C_CELL
+        );
+        assert!(export_dir.join("castep/Pt_00PBE.usp").exists());
+        remove_dir_all(export_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn missing_potentials_copy_nothing() {
+        let (export_dir, potential_dir) = set_up("missing_potentials", false);
+        let error = copy_potentials(&task_in(&export_dir, Some(&potential_dir)))
+            .unwrap_err()
+            .to_string();
+        assert!(error.ends_with("for: C"), "{error}");
+        assert!(!export_dir.join("castep/Pt_00PBE.usp").exists());
+        assert!(!export_dir.join("qe/Pt.UPF").exists());
+        assert!(!export_dir.join("vasp/POTCAR").exists());
+        remove_dir_all(export_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn species_of_pw_input() {
+        assert_eq!(
+            atomic_species(PT_C_PWI),
+            vec![
+                ("Pt".to_string(), "Pt.UPF".to_string()),
+                ("Pt".to_string(), "Pt.UPF".to_string()),
+                ("C".to_string(), "C.UPF".to_string()),
+            ]
+        );
+    }
+}
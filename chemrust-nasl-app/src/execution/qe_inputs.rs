@@ -0,0 +1,278 @@
+use castep_periodic_table::{
+    data::ELEMENT_TABLE,
+    element::{ElementSymbol, LookupElement},
+};
+use chemrust_core::data::{
+    atom::CoreAtomData,
+    geom::coordinates::CoordData,
+    lattice::{CrystalModel, UnitCellParameters},
+};
+
+use crate::{element_data::hubbard_manifold, yaml_parser::TaskTable};
+
+/// Moment (μB) taken as a full `starting_magnetization`, as the valence charge
+/// of the pseudopotentials is not known here.
+const HALF_FILLED_D_SHELL: f64 = 5.0;
+
+/// `pw.x` input of a geometry optimisation of the model, with a
+/// `K_POINTS automatic` grid and the cutoffs of the task. With `edft`,
+/// Marzari-Vanderbilt smearing and a gentler mixing for the metals, otherwise
+/// a narrow Gaussian smearing. The
+/// `fixed` atoms take the `if_pos` flags `0 0 0`. With the initial `spins` of
+/// the atoms, by index, spin polarised with the `starting_magnetization` of
+/// their own species, e.g. `Pt1` beside the `Pt` of the model. The elements of `hubbard_u` take the U (eV) on their open
+/// shells in a `HUBBARD` card, as of `pw.x` 7.1.
+pub fn pw_input<T: CrystalModel>(
+    model: &T,
+    prefix: &str,
+    grid: [u32; 3],
+    task_config: &TaskTable,
+    fixed: &[usize],
+    spins: &[(usize, f64)],
+    hubbard_u: &[(ElementSymbol, f64)],
+) -> String {
+    let lattice_bases = model.get_cell_parameters().lattice_bases();
+    let inverse_bases = lattice_bases
+        .try_inverse()
+        .expect("Matrix is not invertible");
+    let symbols = model.get_atom_data().symbols_repr();
+    let atom_spins: Vec<Option<f64>> = (0..symbols.len())
+        .map(|i| spins.iter().find(|&&(j, _)| j == i).map(|&(_, spin)| spin))
+        .collect();
+    let species = species_of(&symbols, &atom_spins);
+    let species_index = |symbol: ElementSymbol, spin: Option<f64>| {
+        species
+            .iter()
+            .position(|kind| kind.symbol == symbol && kind.spin == spin)
+            .expect("Every atom has its species")
+    };
+    let cutoff = task_config.qe_cutoff();
+    let (smearing, degauss, mixing_beta) = if task_config.edft() {
+        ("marzari-vanderbilt", 0.02, 0.3)
+    } else {
+        ("gaussian", 0.01, 0.7)
+    };
+    let mut content = String::new();
+    content.push_str(&format!(
+        "&CONTROL\n  calculation = 'relax'\n  prefix = '{prefix}'\n  pseudo_dir = './'\n  outdir = './out'\n/\n"
+    ));
+    content.push_str(&format!(
+        "&SYSTEM\n  ibrav = 0\n  nat = {}\n  ntyp = {}\n  ecutwfc = {}\n  ecutrho = {}\n  occupations = 'smearing'\n  smearing = '{smearing}'\n  degauss = {degauss}\n",
+        symbols.len(),
+        species.len(),
+        cutoff.ecutwfc(),
+        cutoff.ecutrho()
+    ));
+    if !spins.is_empty() {
+        content.push_str("  nspin = 2\n");
+        species.iter().enumerate().for_each(|(k, kind)| {
+            if let Some(spin) = kind.spin.filter(|&spin| spin != 0.0) {
+                content.push_str(&format!(
+                    "  starting_magnetization({}) = {:.2}\n",
+                    k + 1,
+                    (spin / HALF_FILLED_D_SHELL).clamp(-1.0, 1.0)
+                ));
+            }
+        });
+    }
+    content.push_str("/\n");
+    content.push_str(&format!(
+        "&ELECTRONS\n  conv_thr = 1.0d-6\n  mixing_beta = {mixing_beta}\n/\n&IONS\n/\n"
+    ));
+    content.push_str("ATOMIC_SPECIES\n");
+    species.iter().for_each(|kind| {
+        let element = ELEMENT_TABLE.get_by_symbol(kind.symbol);
+        content.push_str(&format!(
+            "  {} {:.4} {}.UPF\n",
+            kind.label,
+            element.mass(),
+            element.symbol_to_string()
+        ));
+    });
+    content.push_str("CELL_PARAMETERS angstrom\n");
+    lattice_bases.column_iter().for_each(|v| {
+        content.push_str(&format!("  {:20.16} {:20.16} {:20.16}\n", v[0], v[1], v[2]))
+    });
+    content.push_str("ATOMIC_POSITIONS crystal\n");
+    symbols
+        .iter()
+        .zip(model.get_atom_data().coords_repr().iter())
+        .enumerate()
+        .zip(atom_spins.iter())
+        .for_each(|((i, (&symbol, cd)), &spin)| {
+            let frac = match cd {
+                CoordData::Fractional(frac) => *frac,
+                CoordData::Cartesian(cart) => inverse_bases * cart,
+            };
+            let if_pos = if fixed.contains(&i) { "  0 0 0" } else { "" };
+            content.push_str(&format!(
+                "  {} {:20.16} {:20.16} {:20.16}{}\n",
+                species[species_index(symbol, spin)].label,
+                frac.x,
+                frac.y,
+                frac.z,
+                if_pos
+            ));
+        });
+    content.push_str(&format!(
+        "K_POINTS automatic\n  {} {} {} 0 0 0\n",
+        grid[0], grid[1], grid[2]
+    ));
+    if !hubbard_u.is_empty() {
+        content.push_str("HUBBARD (ortho-atomic)\n");
+        species.iter().for_each(|kind| {
+            let u = hubbard_u
+                .iter()
+                .find(|&&(symbol, _)| symbol == kind.symbol)
+                .map(|&(_, u)| u);
+            if let (Some(u), Some((n, shell))) = (u, hubbard_manifold(kind.symbol)) {
+                content.push_str(&format!("  U {}-{n}{shell} {u:.4}\n", kind.label));
+            }
+        });
+    }
+    content
+}
+
+/// A species of the `ATOMIC_SPECIES`: the atoms of an element with the same
+/// initial spin.
+struct Species {
+    symbol: ElementSymbol,
+    spin: Option<f64>,
+    /// The element, e.g. `Pt`, numbered for its other species, e.g. `Pt1`
+    label: String,
+}
+
+/// Species in the order of their first atoms, so that the new atoms with
+/// an initial spin do not polarise the atoms of the same element in the
+/// model through the `starting_magnetization` of the species.
+fn species_of(symbols: &[ElementSymbol], atom_spins: &[Option<f64>]) -> Vec<Species> {
+    let mut species: Vec<Species> = Vec::new();
+    symbols
+        .iter()
+        .zip(atom_spins.iter())
+        .for_each(|(&symbol, &spin)| {
+            if species
+                .iter()
+                .any(|kind| kind.symbol == symbol && kind.spin == spin)
+            {
+                return;
+            }
+            let name = ELEMENT_TABLE.get_by_symbol(symbol).symbol_to_string();
+            let num_listed = species.iter().filter(|kind| kind.symbol == symbol).count();
+            let label = match num_listed {
+                0 => name,
+                n => format!("{name}{n}"),
+            };
+            species.push(Species {
+                symbol,
+                spin,
+                label,
+            });
+        });
+    species
+}
+
+#[cfg(test)]
+mod test {
+    use castep_periodic_table::element::ElementSymbol;
+
+    use crate::{
+        execution::{
+            format_loader::load_cell_content,
+            test_fixtures::{task, PT_CO},
+        },
+        supportive_data::QeCutoff,
+    };
+
+    use super::pw_input;
+
+    #[test]
+    fn pw_relax_input() {
+        let model = load_cell_content(PT_CO.to_string()).unwrap();
+        let mut task_config = task(true);
+        let input = pw_input(&model, "Pt_CO", [4, 4, 1], &task_config, &[0], &[], &[]);
+        assert!(input.contains("prefix = 'Pt_CO'"));
+        assert!(input.contains("nat = 4\n  ntyp = 3\n"));
+        assert!(input.contains("ecutwfc = 40\n  ecutrho = 320\n"));
+        assert!(input.contains("smearing = 'marzari-vanderbilt'"));
+        assert!(!input.contains("nspin"));
+        // The species in the order of the first atoms of the elements
+        let species: Vec<&str> = input
+            .lines()
+            .skip_while(|line| *line != "ATOMIC_SPECIES")
+            .skip(1)
+            .take(3)
+            .map(|line| line.split_whitespace().next().unwrap())
+            .collect();
+        assert_eq!(species, vec!["Pt", "C", "O"]);
+        // Only the first atom is fixed
+        let positions: Vec<&str> = input
+            .lines()
+            .skip_while(|line| *line != "ATOMIC_POSITIONS crystal")
+            .skip(1)
+            .take(4)
+            .collect();
+        assert!(positions[0].ends_with("0 0 0"));
+        assert!(!positions[1].ends_with("0 0 0"));
+        assert!(input.ends_with("K_POINTS automatic\n  4 4 1 0 0 0\n"));
+        task_config.qe_cutoff = QeCutoff {
+            ecutwfc: 50.0,
+            ecutrho: 500.0,
+        };
+        task_config.edft = false;
+        let input = pw_input(&model, "Pt_CO", [4, 4, 1], &task_config, &[], &[], &[]);
+        assert!(input.contains("ecutwfc = 50\n  ecutrho = 500\n"));
+        assert!(input.contains("smearing = 'gaussian'"));
+    }
+
+    #[test]
+    fn hubbard_card() {
+        let model = load_cell_content(PT_CO.to_string()).unwrap();
+        let input = pw_input(
+            &model,
+            "Pt_CO",
+            [4, 4, 1],
+            &task(false),
+            &[],
+            &[(1, 2.0)],
+            &[(ElementSymbol::Pt, 2.5), (ElementSymbol::O, 1.0)],
+        );
+        // On each species of Pt, O without an open d or f shell
+        assert!(input.ends_with("HUBBARD (ortho-atomic)\n  U Pt-5d 2.5000\n  U Pt1-5d 2.5000\n"));
+        let input = pw_input(&model, "Pt_CO", [4, 4, 1], &task(false), &[], &[], &[]);
+        assert!(!input.contains("HUBBARD"));
+    }
+
+    #[test]
+    fn own_species_of_spins() {
+        let model = load_cell_content(PT_CO.to_string()).unwrap();
+        // The second Pt taken as the new atom
+        let input = pw_input(
+            &model,
+            "Pt_CO",
+            [4, 4, 1],
+            &task(false),
+            &[],
+            &[(1, 2.0)],
+            &[],
+        );
+        assert!(input.contains("ntyp = 4\n"));
+        assert!(input.contains("nspin = 2\n  starting_magnetization(4) = 0.40\n/\n"));
+        let species: Vec<&str> = input
+            .lines()
+            .skip_while(|line| *line != "ATOMIC_SPECIES")
+            .skip(1)
+            .take(4)
+            .collect();
+        assert_eq!(species[0], "  Pt 195.0900 Pt.UPF");
+        assert_eq!(species[3], "  Pt1 195.0900 Pt.UPF");
+        let labels: Vec<&str> = input
+            .lines()
+            .skip_while(|line| *line != "ATOMIC_POSITIONS crystal")
+            .skip(1)
+            .take(4)
+            .map(|line| line.split_whitespace().next().unwrap())
+            .collect();
+        assert_eq!(labels, vec!["Pt", "Pt1", "C", "O"]);
+    }
+}
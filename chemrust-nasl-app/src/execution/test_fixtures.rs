@@ -0,0 +1,46 @@
+use std::path::{Path, PathBuf};
+
+use castep_periodic_table::element::ElementSymbol;
+
+use crate::{
+    interactive_ui::KPointQuality, supportive_data::TargetBondlength, yaml_parser::TaskTable,
+};
+
+/// CO on top of a Pt slab of two atoms, with the vacuum along `c`.
+pub const PT_CO: &str = "%BLOCK LATTICE_CART
+ 5.54 0.00 0.00
+-2.77 4.80 0.00
+ 0.00 0.00 20.0
+%ENDBLOCK LATTICE_CART
+
+%BLOCK POSITIONS_FRAC
+Pt 0.0 0.0 0.1
+Pt 0.5 0.0 0.1
+C 0.0 0.0 0.2
+O 0.0 0.0 0.25
+%ENDBLOCK POSITIONS_FRAC
+";
+
+/// Task adding O to the `PT_CO` model, exported into `out`.
+pub fn task(edft: bool) -> TaskTable {
+    let mut task_config = task_in(Path::new("out"), None);
+    task_config.edft = edft;
+    task_config
+}
+
+/// Task adding O to the `PT_CO` model, exported into the `export_dir`, with
+/// the potentials of the `potential_dir`.
+pub fn task_in(export_dir: &Path, potential_dir: Option<&Path>) -> TaskTable {
+    TaskTable::new(
+        "Pt_CO.cell".to_string(),
+        ElementSymbol::O,
+        TargetBondlength::Uniform(2.0),
+        (0.0, 1.0),
+        (0.0, 1.0),
+        (0.0, 1.0),
+        PathBuf::from(export_dir),
+        potential_dir.map(|dir| dir.display().to_string()),
+        KPointQuality::Coarse,
+        false,
+    )
+}
@@ -0,0 +1,179 @@
+use castep_periodic_table::{
+    data::ELEMENT_TABLE,
+    element::{ElementSymbol, LookupElement},
+};
+
+use crate::{element_data::hubbard_manifold, yaml_parser::TaskTable};
+
+use super::format_loader::Poscar;
+
+/// `ENCUT` (eV) when the task sets no `cut_off_energy`
+const DEFAULT_ENCUT: f64 = 400.0;
+
+/// `INCAR` of a geometry optimisation, with the `cut_off_energy` of the task
+/// as the `ENCUT`. With `edft`, the all-band solver and
+/// Methfessel-Paxton smearing for the metals, otherwise Gaussian smearing.
+/// Spin polarised with the `MAGMOM` of every atom, unless `magmom` is empty.
+/// With any U in `ldau`, the `l` and U of each group of the species lines,
+/// Dudarev's DFT+U.
+pub fn incar(system: &str, task_config: &TaskTable, magmom: &[f64], ldau: &[(i32, f64)]) -> String {
+    let (algo, ismear, sigma) = if task_config.edft() {
+        ("All", 1, 0.1)
+    } else {
+        ("Fast", 0, 0.05)
+    };
+    let mut lines = vec![
+        format!("SYSTEM = {system}"),
+        "PREC = Accurate".to_string(),
+        format!(
+            "ENCUT = {}",
+            task_config.cut_off_energy().unwrap_or(DEFAULT_ENCUT)
+        ),
+        format!("ALGO = {algo}"),
+        format!("ISMEAR = {ismear}"),
+        format!("SIGMA = {sigma}"),
+        "EDIFF = 1E-5".to_string(),
+        "EDIFFG = -0.05".to_string(),
+        "IBRION = 2".to_string(),
+        "ISIF = 2".to_string(),
+        "NSW = 200".to_string(),
+        "LREAL = Auto".to_string(),
+        "LWAVE = .FALSE.".to_string(),
+        "LCHARG = .FALSE.".to_string(),
+    ];
+    if !magmom.is_empty() {
+        lines.push("ISPIN = 2".to_string());
+        lines.push(format!("MAGMOM = {}", compressed_magmom(magmom)));
+    }
+    if ldau.iter().any(|&(l, _)| l >= 0) {
+        let ldaul: Vec<String> = ldau.iter().map(|(l, _)| l.to_string()).collect();
+        let ldauu: Vec<String> = ldau.iter().map(|(_, u)| u.to_string()).collect();
+        let ldauj = vec!["0"; ldau.len()];
+        let lmaxmix = if ldau.iter().any(|&(l, _)| l == 3) {
+            6
+        } else {
+            4
+        };
+        lines.extend([
+            "LDAU = .TRUE.".to_string(),
+            "LDAUTYPE = 2".to_string(),
+            format!("LDAUL = {}", ldaul.join(" ")),
+            format!("LDAUU = {}", ldauu.join(" ")),
+            format!("LDAUJ = {}", ldauj.join(" ")),
+            format!("LMAXMIX = {lmaxmix}"),
+        ]);
+    }
+    lines.join("\n") + "\n"
+}
+
+/// The `l` and U of each group of the species lines, `(-1, 0.0)` for the
+/// elements without a U.
+pub fn ldau(poscar: &Poscar, hubbard_u: &[(ElementSymbol, f64)]) -> Vec<(i32, f64)> {
+    poscar
+        .species_names()
+        .iter()
+        .map(|&name| {
+            hubbard_u
+                .iter()
+                .find(|&&(symbol, _)| {
+                    ELEMENT_TABLE.get_by_symbol(symbol).symbol_to_string() == name
+                })
+                .and_then(|&(symbol, u)| hubbard_manifold(symbol).map(|(_, shell)| (shell, u)))
+                .map(|(shell, u)| (if shell == 'f' { 3 } else { 2 }, u))
+                .unwrap_or((-1, 0.0))
+        })
+        .collect()
+}
+
+/// The moments with the runs of equal values written as `N*value`, e.g. `35*0 2`.
+fn compressed_magmom(magmom: &[f64]) -> String {
+    let mut runs: Vec<(usize, f64)> = Vec::new();
+    magmom.iter().for_each(|&moment| match runs.last_mut() {
+        Some((count, value)) if *value == moment => *count += 1,
+        _ => runs.push((1, moment)),
+    });
+    runs.iter()
+        .map(|&(count, value)| {
+            if count > 1 {
+                format!("{count}*{value}")
+            } else {
+                format!("{value}")
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// `KPOINTS` of a Gamma-centred grid.
+pub fn kpoints(grid: [u32; 3]) -> String {
+    format!(
+        "Automatic mesh\n0\nGamma\n  {} {} {}\n  0 0 0\n",
+        grid[0], grid[1], grid[2]
+    )
+}
+
+/// The potentials to concatenate into the `POTCAR`, in the order of the species lines.
+pub fn potcar_spec(poscar: &Poscar) -> String {
+    poscar
+        .species_names()
+        .iter()
+        .map(|name| format!("{name}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use castep_periodic_table::element::ElementSymbol;
+
+    use crate::execution::{format_loader::Poscar, test_fixtures::task};
+
+    use super::{incar, ldau};
+
+    #[test]
+    fn encut_of_task() {
+        let mut task_config = task(false);
+        assert!(incar("CeO2", &task_config, &[], &[]).contains("ENCUT = 400\n"));
+        task_config.cut_off_energy = Some(520.0);
+        assert!(incar("CeO2", &task_config, &[], &[]).contains("ENCUT = 520\n"));
+    }
+
+    #[test]
+    fn dudarev_u_of_species() {
+        let poscar = Poscar::parse(
+            "CeO2 with Fe
+1.0
+  5.41  0.00  0.00
+  0.00  5.41  0.00
+  0.00  0.00 20.00
+Fe O Ce
+1 2 1
+Direct
+ 0.0 0.0 0.30
+ 0.25 0.25 0.20
+ 0.75 0.75 0.20
+ 0.0 0.0 0.10
+",
+        )
+        .unwrap();
+        let ldau = ldau(
+            &poscar,
+            &[(ElementSymbol::Ce, 5.0), (ElementSymbol::Fe, 5.3)],
+        );
+        // In the order of the species lines, O without a U
+        assert_eq!(ldau, vec![(2, 5.3), (-1, 0.0), (3, 5.0)]);
+        let written = incar("CeO2", &task(false), &[], &ldau);
+        assert!(written.ends_with(
+            "LDAU = .TRUE.\nLDAUTYPE = 2\nLDAUL = 2 -1 3\nLDAUU = 5.3 0 5\nLDAUJ = 0 0 0\nLMAXMIX = 6\n"
+        ));
+        // The d states only
+        let written = incar("CeO2", &task(false), &[], &[(2, 5.3), (-1, 0.0), (-1, 0.0)]);
+        assert!(written.contains("LMAXMIX = 4\n"));
+        let written = incar(
+            "CeO2",
+            &task(false),
+            &[],
+            &[(-1, 0.0), (-1, 0.0), (-1, 0.0)],
+        );
+        assert!(!written.contains("LDAU"));
+    }
+}
@@ -1,14 +1,17 @@
 use std::{fmt::Display, str::FromStr};
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum RunMode {
     /// Generate seed files without copying potentials
+    #[default]
     Fast,
     /// Generate seed files and copy potentials
     Full,
     /// Copy potentials after seed files generation
     Post,
-    /// Reorganize
+    /// Report the files to write without writing them
     Dryrun,
     /// Debug
     Debug,
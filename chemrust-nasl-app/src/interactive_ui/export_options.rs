@@ -14,13 +14,14 @@ pub struct ExportOptions {
 }
 
 impl ExportOptions {
+    /// The `species_name` is the element symbol of the new atom, or the name of the adsorbate.
     pub fn new(
         new_element: &Element,
-        bondlength: f64,
+        species_name: &str,
+        bondlength: Option<f64>,
         model_name: &str,
     ) -> Result<ExportOptions, InquireError> {
-        let export_dir =
-            Self::ask_export_dir(&new_element.symbol_to_string(), bondlength, model_name)?;
+        let export_dir = Self::ask_export_dir(species_name, bondlength, model_name)?;
         let potential_dir = Self::ask_potential_dir()?;
         let kpoint_quality = Self::ask_kpoint_quality()?;
         let edft = Self::ask_edft(new_element)?;
@@ -34,14 +35,18 @@ impl ExportOptions {
         })
     }
     fn ask_export_dir(
-        element_symbol: &str,
-        bondlength: f64,
+        species_name: &str,
+        bondlength: Option<f64>,
         model_name: &str,
     ) -> Result<String, InquireError> {
+        let default_dir = match bondlength {
+            Some(bondlength) => format!("{}_{}_{}", species_name, bondlength, model_name),
+            None => format!("{}_{}", species_name, model_name),
+        };
         Text::new("Please name the directory for exported seed files: ")
             .with_help_message("Default: element_bondlength_base-model-name")
             .with_autocomplete(FilePathCompleter::default())
-            .with_default(&format!("{}_{}_{}", element_symbol, bondlength, model_name))
+            .with_default(&default_dir)
             .prompt()
     }
     fn ask_potential_dir() -> Result<String, InquireError> {
@@ -19,6 +19,17 @@ impl Display for KPointQuality {
     }
 }
 
+impl KPointQuality {
+    /// Spacing (Å⁻¹) of the k-point grid, without the factor of 2π.
+    pub fn kpoint_spacing(&self) -> f64 {
+        match self {
+            KPointQuality::Coarse => 0.07,
+            KPointQuality::Medium => 0.05,
+            KPointQuality::Fine => 0.04,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct ParseKPointQualityError;
 
@@ -1,9 +1,20 @@
 use std::path::Path;
 
-use castep_periodic_table::element::Element;
-use inquire::{required, validator::Validation, CustomType, InquireError, Text};
+use castep_periodic_table::{
+    data::ELEMENT_TABLE,
+    element::{Element, LookupElement},
+};
+use inquire::{
+    required, validator::Validation, Confirm, CustomType, InquireError, MultiSelect, Select, Text,
+};
 
-use crate::{supportive_data::FractionalCoordRange, yaml_parser::TaskTable};
+use crate::{
+    adsorbate_library::{AdsorbatePreset, ADSORBATE_PRESETS},
+    supportive_data::{
+        AdsorbateSpec, ExportLayout, FractionalCoordRange, KeepSide, TargetBondlength,
+    },
+    yaml_parser::{TaskTable, DEFAULT_SYMMETRY_TOLERANCE},
+};
 
 use super::{filepath_completer::FilePathCompleter, ExportOptions};
 
@@ -11,26 +22,40 @@ use super::{filepath_completer::FilePathCompleter, ExportOptions};
 pub struct RunOptions {
     filepath: String,
     new_element: Element,
-    target_bondlength: f64,
+    adsorbate: Option<&'static AdsorbatePreset>,
+    target_bondlength: Option<f64>,
     x_range: FractionalCoordRange,
     y_range: FractionalCoordRange,
     z_range: FractionalCoordRange,
+    periodic: [bool; 3],
+    keep_side: KeepSide,
+    reduce_by_symmetry: bool,
 }
 
 impl RunOptions {
     fn ask_filename() -> Result<String, InquireError> {
         let current_dir = std::env::current_dir().unwrap();
         let help_message = format!("Current directory: {}", current_dir.to_string_lossy());
-        Text::new("Filepath of the model cell file:")
+        Text::new("Filepath of the model file (`.cell`, `.cif` or VASP `POSCAR`):")
             .with_autocomplete(FilePathCompleter::default())
             .with_validator(required!("This field is required"))
             .with_validator(|input: &str| {
-                if input.split('.').last().unwrap() == "cell" {
+                let filename = Path::new(input)
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or_default();
+                if matches!(
+                    input.split('.').last().unwrap(),
+                    "cell" | "cif" | "vasp" | "poscar"
+                ) || filename.starts_with("POSCAR")
+                    || filename.starts_with("CONTCAR")
+                {
                     Ok(Validation::Valid)
                 } else {
                     Ok(Validation::Invalid(
                         inquire::validator::ErrorMessage::Custom(
-                            "Please enter the filepath of a `.cell` file".into(),
+                            "Please enter the filepath of a `.cell`, `.cif` or `POSCAR` file"
+                                .into(),
                         ),
                     ))
                 }
@@ -38,6 +63,31 @@ impl RunOptions {
             .with_help_message(&help_message)
             .prompt()
     }
+    fn ask_adsorbate() -> Result<Option<&'static AdsorbatePreset>, InquireError> {
+        let options: Vec<String> = ["Single atom".to_string()]
+            .into_iter()
+            .chain(ADSORBATE_PRESETS.iter().map(|preset| preset.to_string()))
+            .collect();
+        let choice = Select::new("Place a single atom or a preset adsorbate?", options)
+            .with_help_message("The anchor atom of the adsorbate bonds to the sites, e.g. O of OOH")
+            .prompt()?;
+        Ok(ADSORBATE_PRESETS
+            .iter()
+            .find(|preset| preset.name() == choice))
+    }
+    fn ask_preset_bondlength(preset: &AdsorbatePreset) -> Result<Option<f64>, InquireError> {
+        let use_default = Confirm::new(&format!(
+            "Use the default bondlengths of {} to the elements in the model?",
+            preset
+        ))
+        .with_default(true)
+        .prompt()?;
+        if use_default {
+            Ok(None)
+        } else {
+            Ok(Some(Self::ask_bondlength()?))
+        }
+    }
     fn ask_element() -> Result<Element, InquireError> {
         CustomType::<Element>::new("Element symbol of the new atom: ").prompt()
     }
@@ -61,20 +111,66 @@ impl RunOptions {
             .prompt()?;
         Ok(FractionalCoordRange::new(min, max))
     }
+    fn ask_periodic() -> Result<[bool; 3], InquireError> {
+        let axes = vec!["a", "b", "c"];
+        let periodic_axes = MultiSelect::new("Which lattice vectors are periodic?", axes.clone())
+            .with_help_message("Neighbours across the boundaries of periodic directions are considered. Deselect the vacuum direction of a slab if it is not thick enough; press enter for default value (all)")
+            .with_default(&[0, 1, 2])
+            .prompt()?;
+        Ok([0, 1, 2].map(|i| periodic_axes.contains(&axes[i])))
+    }
+    fn ask_keep_side() -> Result<KeepSide, InquireError> {
+        let options = vec![
+            "Side of the surface normal",
+            "Both sides",
+            "Away from the centre of mass",
+        ];
+        let choice = Select::new(
+            "Which point to keep when two atoms and a third one meet at two points?",
+            options.clone(),
+        )
+        .with_help_message("Keep both for adsorption on both faces of 2D materials, or away from the centre for bulk interstitials")
+        .prompt()?;
+        let keep_side = match options.iter().position(|&opt| opt == choice) {
+            Some(1) => KeepSide::Both,
+            Some(2) => KeepSide::AwayFromCentre,
+            _ => KeepSide::Normal,
+        };
+        Ok(keep_side)
+    }
+    fn ask_reduce_by_symmetry() -> Result<bool, InquireError> {
+        Confirm::new("Export only one site of each group of symmetry-equivalent sites?")
+            .with_help_message("The number of equivalent sites is listed in the summary")
+            .with_default(false)
+            .prompt()
+    }
     pub fn new() -> Result<RunOptions, InquireError> {
         let filename = Self::ask_filename()?;
-        let new_element = Self::ask_element()?;
-        let target_bondlength = Self::ask_bondlength()?;
+        let adsorbate = Self::ask_adsorbate()?;
+        let (new_element, target_bondlength) = match adsorbate {
+            Some(preset) => (
+                ELEMENT_TABLE.get_by_symbol(preset.anchor_symbol()).clone(),
+                Self::ask_preset_bondlength(preset)?,
+            ),
+            None => (Self::ask_element()?, Some(Self::ask_bondlength()?)),
+        };
         let x_range = Self::ask_frac_range("x-axis")?;
         let y_range = Self::ask_frac_range("y-axis")?;
         let z_range = Self::ask_frac_range("z-axis")?;
+        let periodic = Self::ask_periodic()?;
+        let keep_side = Self::ask_keep_side()?;
+        let reduce_by_symmetry = Self::ask_reduce_by_symmetry()?;
         Ok(RunOptions {
             filepath: filename,
             new_element: new_element.clone(),
+            adsorbate,
             target_bondlength,
             x_range,
             y_range,
             z_range,
+            periodic,
+            keep_side,
+            reduce_by_symmetry,
         })
     }
 
@@ -86,20 +182,48 @@ impl RunOptions {
             .to_str()
             .unwrap();
         let new_element = self.new_element().symbol();
-        let target_bondlength = self.target_bondlength();
-        let export_options =
-            ExportOptions::new(&self.new_element, self.target_bondlength(), model_seedname)?;
+        let target_bondlength = self.target_bondlength().map(TargetBondlength::Uniform);
+        let species_name = match self.adsorbate {
+            Some(preset) => preset.name().to_string(),
+            None => self.new_element.symbol_to_string(),
+        };
+        let export_options = ExportOptions::new(
+            &self.new_element,
+            &species_name,
+            self.target_bondlength(),
+            model_seedname,
+        )?;
         Ok(TaskTable {
             model_path,
-            new_element,
+            new_element: Some(new_element),
             target_bondlength,
             x_range: (self.x_range.min(), self.x_range.max()),
             y_range: (self.y_range.min(), self.y_range.max()),
             z_range: (self.z_range.min(), self.z_range.max()),
+            periodic: self.periodic,
+            exclusion_radius: None,
+            keep_side: self.keep_side,
+            surface_normal: None,
+            adsorbate: self
+                .adsorbate
+                .map(|preset| AdsorbateSpec::Preset(preset.name().to_string())),
+            symmetry_tolerance: self
+                .reduce_by_symmetry
+                .then_some(DEFAULT_SYMMETRY_TOLERANCE),
+            fingerprint: None,
+            export_format: None,
+            extxyz: false,
+            fix_atoms: None,
+            initial_spin: None,
+            hubbard_u: None,
+            job_script: None,
+            export_layout: ExportLayout::default(),
             export_dir: export_options.export_dir().into(),
             potential_dir: Some(export_options.potential_dir().into()),
             kpoint_quality: export_options.kpoint_quality().clone(),
+            kpoint_spacing: None,
             edft: export_options.edft(),
+            run_mode: export_options.run_mode(),
         })
     }
 
@@ -111,7 +235,8 @@ impl RunOptions {
         &self.new_element
     }
 
-    pub fn target_bondlength(&self) -> f64 {
+    /// `None` when the default bondlengths of the preset adsorbate are used.
+    pub fn target_bondlength(&self) -> Option<f64> {
         self.target_bondlength
     }
 
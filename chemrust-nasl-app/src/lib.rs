@@ -1,4 +1,6 @@
+pub mod adsorbate_library;
 pub mod arg_parser;
+pub mod element_data;
 pub mod error;
 pub mod execution;
 pub mod interactive_ui;
@@ -6,15 +8,55 @@ pub mod supportive_data;
 pub mod yaml_parser;
 
 use error::RunError;
-pub use interactive_ui::KPointQuality;
+pub use interactive_ui::{KPointQuality, RunMode};
 pub use yaml_parser::TaskTable;
 
-pub fn run_by_table(task_table: &TaskTable) -> Result<(), RunError> {
-    let results = execution::search(task_table)?;
-    execution::export_results_in_cell(task_table, &results)?;
+/// Run the task by its `run_mode`.
+/// # Returns
+/// Whether the seeds of the search have been written, `false` for the runs
+/// not writing them, e.g. `Post`, and when no site is found.
+pub fn run_by_table(task_table: &TaskTable) -> Result<bool, RunError> {
+    match task_table.run_mode() {
+        RunMode::Post => return copy_potentials(task_table).map(|_| false),
+        RunMode::Clean => return clean_export_dir(task_table).map(|_| false),
+        _ => (),
+    }
+    let (mul, sing, doub) = execution::search_and_export(task_table)?;
+    if task_table.run_mode() == RunMode::Dryrun {
+        return Ok(false);
+    }
+    if mul == 0 && sing == 0 && doub == 0 {
+        println!("No avaliable results. You may check if the search ranges cover the atoms in the `.cell`, and if the periodic directions are set correctly.");
+        return Ok(false);
+    }
     println!(
         "Results have been written to {}",
         task_table.export_dir().display()
     );
+    if task_table.run_mode() == RunMode::Full {
+        copy_potentials(task_table)?;
+    }
+    Ok(true)
+}
+
+/// Copy the potentials for the seeds in the `export_dir`.
+pub fn copy_potentials(task_table: &TaskTable) -> Result<(), RunError> {
+    let num_seeds = execution::copy_potentials(task_table)?;
+    println!(
+        "Potentials have been copied for {} seeds in {}",
+        num_seeds,
+        task_table.export_dir().display()
+    );
+    Ok(())
+}
+
+/// Remove the files written by the previous runs into the `export_dir`.
+pub fn clean_export_dir(task_table: &TaskTable) -> Result<(), RunError> {
+    let num_removed = execution::clean_export_dir(task_table.export_dir())?;
+    println!(
+        "Removed {} files of the previous runs from {}",
+        num_removed,
+        task_table.export_dir().display()
+    );
     Ok(())
 }
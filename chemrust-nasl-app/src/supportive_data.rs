@@ -1,4 +1,12 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
+
+use castep_periodic_table::element::ElementSymbol;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    adsorbate_library::{find_preset, AdsorbatePreset},
+    element_data::{default_hubbard_u, default_initial_spin, hubbard_manifold},
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct FractionalCoordRange(f64, f64);
@@ -27,3 +35,327 @@ impl FractionalCoordRange {
         self.1
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+/// Target bondlength of the new atom, either to every atom in the model,
+/// a range of accepted bondlengths to every atom, e.g. `[2.1, 2.3]`,
+/// or specific to the element of the bonded atom, e.g. `{O: 2.0, Ni: 2.5}`
+pub enum TargetBondlength {
+    Uniform(f64),
+    Range(f64, f64),
+    PerElement(HashMap<ElementSymbol, f64>),
+}
+
+impl TargetBondlength {
+    pub fn for_element(&self, symbol: ElementSymbol) -> Option<f64> {
+        match self {
+            TargetBondlength::Uniform(v) => Some(*v),
+            TargetBondlength::Range(min, max) => Some((min + max) / 2.0),
+            TargetBondlength::PerElement(table) => table.get(&symbol).copied(),
+        }
+    }
+    /// The accepted `(min, max)` bondlengths, if a range is given.
+    pub fn range(&self) -> Option<(f64, f64)> {
+        if let TargetBondlength::Range(min, max) = self {
+            Some((*min, *max))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Which point to keep when a coordination circle cuts an atom sphere at two points
+pub enum KeepSide {
+    /// Both points, e.g. for adsorption on both faces of 2D materials
+    Both,
+    /// The point on the side of the surface normal
+    #[default]
+    Normal,
+    /// The point farther from the centre of mass of the model, e.g. for bulk interstitials
+    AwayFromCentre,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A small molecule placed at the sites instead of a single atom, read from
+/// an `.xyz` or `.cell` fragment.
+pub struct AdsorbateFragment {
+    pub(crate) path: String,
+    /// Index of the atom bonding to the sites, counting from 0 in the fragment
+    pub(crate) anchor: usize,
+    /// Number of orientations sampled by turning about the site normal
+    #[serde(default = "default_rotations")]
+    pub(crate) rotations: usize,
+}
+
+impl AdsorbateFragment {
+    pub fn path(&self) -> &str {
+        self.path.as_ref()
+    }
+    pub fn anchor(&self) -> usize {
+        self.anchor
+    }
+    pub fn rotations(&self) -> usize {
+        self.rotations
+    }
+}
+
+fn default_rotations() -> usize {
+    8
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Cluster the sites with similar chemical environments, for models without
+/// exact symmetry, e.g. relaxed surfaces.
+pub struct FingerprintClustering {
+    /// Largest difference (Å) of the neighbour distances within a cluster
+    pub(crate) threshold: f64,
+    /// Radius (Å) around the sites to collect the neighbours
+    #[serde(default = "default_fingerprint_cutoff")]
+    pub(crate) cutoff: f64,
+}
+
+impl FingerprintClustering {
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+    pub fn cutoff(&self) -> f64 {
+        self.cutoff
+    }
+}
+
+fn default_fingerprint_cutoff() -> f64 {
+    4.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+/// The molecule to place at the sites, either a preset by name, e.g. `OOH`,
+/// or a fragment file.
+pub enum AdsorbateSpec {
+    Preset(String),
+    Fragment(AdsorbateFragment),
+}
+
+impl AdsorbateSpec {
+    /// `None` for fragments or unknown names.
+    pub fn preset(&self) -> Option<&'static AdsorbatePreset> {
+        match self {
+            AdsorbateSpec::Preset(name) => find_preset(name),
+            AdsorbateSpec::Fragment(_) => None,
+        }
+    }
+    pub fn rotations(&self) -> usize {
+        match self {
+            AdsorbateSpec::Preset(_) => default_rotations(),
+            AdsorbateSpec::Fragment(fragment) => fragment.rotations(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Format of the exported seeds
+pub enum ExportFormat {
+    /// `.cell` and `.cif`
+    Castep,
+    /// A directory per seed with `POSCAR` and the `INCAR`, `KPOINTS`, `POTCAR.spec` stubs
+    Vasp,
+    /// `pw.x` inputs `.pwi`
+    #[serde(alias = "qe")]
+    QuantumEspresso,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Plane-wave cutoffs (Ry) of the `pw.x` inputs
+pub struct QeCutoff {
+    #[serde(default = "default_ecutwfc")]
+    pub(crate) ecutwfc: f64,
+    /// Cutoff of the charge density, usually 8 to 12 times `ecutwfc` for the
+    /// ultrasoft and PAW potentials
+    #[serde(default = "default_ecutrho")]
+    pub(crate) ecutrho: f64,
+}
+
+impl QeCutoff {
+    pub fn ecutwfc(&self) -> f64 {
+        self.ecutwfc
+    }
+    pub fn ecutrho(&self) -> f64 {
+        self.ecutrho
+    }
+}
+
+impl Default for QeCutoff {
+    fn default() -> Self {
+        Self {
+            ecutwfc: default_ecutwfc(),
+            ecutrho: default_ecutrho(),
+        }
+    }
+}
+
+fn default_ecutwfc() -> f64 {
+    40.0
+}
+
+fn default_ecutrho() -> f64 {
+    320.0
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Arrangement of the seeds in the `export_dir`
+pub enum ExportLayout {
+    /// The files of all the seeds side by side; the VASP seeds are still
+    /// directories
+    #[default]
+    Flat,
+    /// A directory per seed, `<export_dir>/<seed>/`, with the files of the
+    /// seed, its potentials and job script, and a `metadata.yaml` of the site
+    PerSeed,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+/// Atoms of the model fixed in the geometry optimisations of the seeds. An
+/// atom is fixed when any of the rules fixes it.
+pub struct FixAtoms {
+    /// Fix the atoms below this fractional coordinate along the lattice vector
+    /// crossing the vacuum, or `c` of bulk models
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) below: Option<f64>,
+    /// Fix this number of atomic layers from the bottom of the slab
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) bottom_layers: Option<usize>,
+    /// Fix all but the atoms coordinating the site
+    #[serde(default)]
+    pub(crate) except_site: bool,
+}
+
+impl FixAtoms {
+    pub fn below(&self) -> Option<f64> {
+        self.below
+    }
+    pub fn bottom_layers(&self) -> Option<usize> {
+        self.bottom_layers
+    }
+    pub fn except_site(&self) -> bool {
+        self.except_site
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+/// Initial spin (μB) of the new atoms, either `true` for the spin of the
+/// free atom of the d-block metals and the lanthanides, or the spins of some
+/// elements, e.g. `{Fe: 2.0, Pt: 0.0}`, the others taking the defaults.
+pub enum InitialSpin {
+    Defaults(bool),
+    PerElement(HashMap<ElementSymbol, f64>),
+}
+
+impl InitialSpin {
+    /// `None` for the atoms starting unpolarised, or a spin of zero.
+    pub fn for_element(&self, symbol: ElementSymbol) -> Option<f64> {
+        let spin = match self {
+            InitialSpin::Defaults(true) => default_initial_spin(symbol),
+            InitialSpin::Defaults(false) => None,
+            InitialSpin::PerElement(table) => table
+                .get(&symbol)
+                .copied()
+                .or_else(|| default_initial_spin(symbol)),
+        };
+        spin.filter(|&spin| spin != 0.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+/// DFT+U of the d and f elements in the models, either `true` for the
+/// default U of each element, or the U (eV) of some elements, e.g.
+/// `{Fe: 4.0, Ce: 0.0}`, the others taking the defaults.
+pub enum HubbardU {
+    Defaults(bool),
+    PerElement(HashMap<ElementSymbol, f64>),
+}
+
+impl HubbardU {
+    /// `None` for the elements without an open d or f shell, or a U of zero.
+    pub fn for_element(&self, symbol: ElementSymbol) -> Option<f64> {
+        hubbard_manifold(symbol)?;
+        let u = match self {
+            HubbardU::Defaults(true) => default_hubbard_u(symbol),
+            HubbardU::Defaults(false) => None,
+            HubbardU::PerElement(table) => table
+                .get(&symbol)
+                .copied()
+                .or_else(|| default_hubbard_u(symbol)),
+        };
+        u.filter(|&u| u != 0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// Batch system of the job scripts
+pub enum Scheduler {
+    #[default]
+    Slurm,
+    /// PBS Pro, with the `#PBS -J` job arrays
+    Pbs,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Job scripts to submit the exported seeds, one per seed, or one array job
+/// over all the seeds.
+pub struct JobScript {
+    #[serde(default)]
+    pub(crate) scheduler: Scheduler,
+    /// Path to a template in place of the built-in one of the `scheduler`,
+    /// with the placeholders `{job_name}`, `{seed}`, `{cores}`, `{walltime}`,
+    /// `{executable}` and `{command}`, and `{num_seeds}`, `{seed_list}` for
+    /// the array jobs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) template: Option<String>,
+    #[serde(default = "default_cores")]
+    pub(crate) cores: usize,
+    #[serde(default = "default_walltime")]
+    pub(crate) walltime: String,
+    /// `castep.mpi`, `vasp_std` or `pw.x` by the export format when not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) executable: Option<String>,
+    /// One array job over the seeds listed in a file, instead of a script per seed
+    #[serde(default)]
+    pub(crate) array: bool,
+}
+
+impl JobScript {
+    pub fn scheduler(&self) -> Scheduler {
+        self.scheduler
+    }
+    pub fn template(&self) -> Option<&str> {
+        self.template.as_deref()
+    }
+    pub fn cores(&self) -> usize {
+        self.cores
+    }
+    pub fn walltime(&self) -> &str {
+        &self.walltime
+    }
+    pub fn executable(&self) -> Option<&str> {
+        self.executable.as_deref()
+    }
+    pub fn array(&self) -> bool {
+        self.array
+    }
+}
+
+fn default_cores() -> usize {
+    32
+}
+
+fn default_walltime() -> String {
+    "24:00:00".to_string()
+}
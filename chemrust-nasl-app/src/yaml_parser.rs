@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     error::Error,
     path::{Path, PathBuf},
 };
@@ -7,30 +8,119 @@ use castep_periodic_table::{
     data::ELEMENT_TABLE,
     element::{Element, ElementSymbol, LookupElement},
 };
+use chemrust_nasl::Periodicity;
 use serde::{Deserialize, Serialize};
 
-use crate::{interactive_ui::KPointQuality, supportive_data::FractionalCoordRange};
+use crate::{
+    adsorbate_library::AdsorbatePreset,
+    interactive_ui::{KPointQuality, RunMode},
+    supportive_data::{
+        AdsorbateSpec, ExportFormat, ExportLayout, FingerprintClustering, FixAtoms,
+        FractionalCoordRange, HubbardU, InitialSpin, JobScript, KeepSide, QeCutoff,
+        TargetBondlength,
+    },
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 /// A config struct
 pub struct TaskTable {
     pub(crate) model_path: String,
-    pub(crate) new_element: ElementSymbol,
-    pub(crate) target_bondlength: f64,
+    /// Can be left out with a preset `adsorbate`, taking its anchor atom.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) new_element: Option<ElementSymbol>,
+    /// Can be left out with a preset `adsorbate`, taking its default
+    /// bondlengths to the elements in the model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) target_bondlength: Option<TargetBondlength>,
     pub(crate) x_range: (f64, f64),
     pub(crate) y_range: (f64, f64),
     pub(crate) z_range: (f64, f64),
+    /// Periodic along the lattice vectors `a`, `b`, `c`
+    #[serde(default = "default_periodic")]
+    pub(crate) periodic: [bool; 3],
+    /// Minimum approach distance of the new atom to the atoms of each element,
+    /// e.g. `{H: 1.2}`. The elements not listed take 0.75 of the summed covalent
+    /// radii, capped by the lowest accepted bondlength.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) exclusion_radius: Option<HashMap<ElementSymbol, f64>>,
+    /// Which of the two points to keep when a coordination circle cuts an atom
+    /// sphere at two points: `both`, `normal` or `away_from_centre`.
+    #[serde(default)]
+    pub(crate) keep_side: KeepSide,
+    /// Surface normal in cartesian coordinates. Detected from the vacuum of
+    /// slab models when not given, otherwise `[0.0, 0.0, 1.0]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) surface_normal: Option<[f64; 3]>,
+    /// Place a molecule at the sites by its anchor atom, instead of a single
+    /// atom. Either a preset name, e.g. `OOH`, or a fragment whose anchor
+    /// should be of the `new_element`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) adsorbate: Option<AdsorbateSpec>,
+    /// Largest displacement (Å) of the atoms by a symmetry operation of the
+    /// model, e.g. `0.1`. Only one site of each group of symmetry-equivalent
+    /// sites is exported when given; all sites are exported by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) symmetry_tolerance: Option<f64>,
+    /// Keep one site of each cluster of sites with similar chemical
+    /// environments, e.g. `{threshold: 0.1, cutoff: 4.0}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) fingerprint: Option<FingerprintClustering>,
+    /// `castep`, `vasp` or `quantum_espresso`. Follows the model when not given: `vasp` for
+    /// `POSCAR`/`CONTCAR`, otherwise `castep`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) export_format: Option<ExportFormat>,
+    /// Also write the structures of all the sites as the frames of one
+    /// extended XYZ file, e.g. for screening with machine-learned potentials.
+    #[serde(default)]
+    pub(crate) extxyz: bool,
+    /// Atoms of the model to fix, e.g. `{bottom_layers: 2}`, written as
+    /// `IONIC_CONSTRAINTS`, selective dynamics or the `if_pos` flags.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) fix_atoms: Option<FixAtoms>,
+    /// Initial spin (μB) of the new atoms, `true` for the spin of the free
+    /// atom of the d-block metals and the lanthanides, or e.g. `{Fe: 2.0}` to
+    /// set some of them. The atoms start unpolarised when not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) initial_spin: Option<InitialSpin>,
+    /// DFT+U of the d and f elements, `true` for the default U of each
+    /// element, or e.g. `{Fe: 4.0}` to set some of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) hubbard_u: Option<HubbardU>,
+    /// `ecutwfc` and `ecutrho` (Ry) of the `pw.x` inputs, `{ecutwfc: 40,
+    /// ecutrho: 320}` by default.
+    #[serde(default)]
+    pub(crate) qe_cutoff: QeCutoff,
+    /// `cut_off_energy` (eV) of the CASTEP `.param` and the `ENCUT` of the
+    /// VASP `INCAR`, e.g. `450.0`. Left to the `basis_precision` of CASTEP,
+    /// and 400 eV for VASP, when not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cut_off_energy: Option<f64>,
+    /// Write the job scripts of the seeds, e.g. `{scheduler: pbs, cores: 64}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) job_script: Option<JobScript>,
+    /// `flat` (default), or `per_seed` for a directory of each seed
+    #[serde(default)]
+    pub(crate) export_layout: ExportLayout,
     pub(crate) export_dir: PathBuf,
     pub(crate) potential_dir: Option<String>,
     pub(crate) kpoint_quality: KPointQuality,
+    /// Spacing (Å⁻¹) of the k-points, in place of the one of `kpoint_quality`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) kpoint_spacing: Option<f64>,
     pub(crate) edft: bool,
+    /// `Fast` (default) exports the seeds, `Full` also copies the potentials
+    /// from `potential_dir`, and `Post` only copies the potentials for the
+    /// seeds exported before. `Dryrun` reports the files to write without
+    /// writing them, and `Clean` removes the files of the previous runs.
+    #[serde(default)]
+    pub(crate) run_mode: RunMode,
 }
 
 impl TaskTable {
     pub fn new(
         model_path: String,
         new_element: ElementSymbol,
-        target_bondlength: f64,
+        target_bondlength: TargetBondlength,
         x_range: (f64, f64),
         y_range: (f64, f64),
         z_range: (f64, f64),
@@ -41,34 +131,96 @@ impl TaskTable {
     ) -> Self {
         Self {
             model_path,
-            new_element,
-            target_bondlength,
+            new_element: Some(new_element),
+            target_bondlength: Some(target_bondlength),
             x_range,
             y_range,
             z_range,
+            periodic: default_periodic(),
+            exclusion_radius: None,
+            keep_side: KeepSide::default(),
+            surface_normal: None,
+            adsorbate: None,
+            symmetry_tolerance: None,
+            fingerprint: None,
+            export_format: None,
+            extxyz: false,
+            fix_atoms: None,
+            initial_spin: None,
+            hubbard_u: None,
+            qe_cutoff: QeCutoff::default(),
+            cut_off_energy: None,
+            job_script: None,
+            export_layout: ExportLayout::default(),
             export_dir,
             potential_dir,
             kpoint_quality,
+            kpoint_spacing: None,
             edft,
+            run_mode: RunMode::default(),
         }
     }
 
     pub fn load_task_table<P: AsRef<Path>>(filepath: P) -> Result<Self, Box<dyn Error>> {
         let table_src = std::fs::File::open(filepath)?;
-        let table = serde_yaml::from_reader(table_src)?;
+        let table: TaskTable = serde_yaml::from_reader(table_src)?;
+        table.validate()?;
         Ok(table)
     }
 
+    /// Checks the settings that serde can not.
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(AdsorbateSpec::Preset(name)) = &self.adsorbate {
+            if self.preset().is_none() {
+                return Err(format!("Unknown adsorbate preset `{name}`").into());
+            }
+        }
+        if self.new_element.is_none() && self.preset().is_none() {
+            return Err("Either `new_element` or a preset `adsorbate` is required".into());
+        }
+        if self.target_bondlength.is_none() && self.preset().is_none() {
+            return Err("Either `target_bondlength` or a preset `adsorbate` is required".into());
+        }
+        if let Some((min, max)) = self.target_bondlength().and_then(|target| target.range()) {
+            if !(0.0 < min && min <= max) {
+                return Err(format!(
+                    "Invalid `target_bondlength` range [{min}, {max}], expecting 0 < min <= max"
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
     pub fn model_path(&self) -> &str {
         self.model_path.as_ref()
     }
 
+    /// The `new_element`, or the anchor atom of the preset adsorbate.
+    /// # Panics
+    /// Panics if neither is given, which `load_task_table` rejects.
     pub fn new_element(&self) -> &Element {
-        ELEMENT_TABLE.get_by_symbol(self.new_element)
+        let symbol = self
+            .new_element
+            .or_else(|| self.preset().map(|preset| preset.anchor_symbol()))
+            .expect("Either `new_element` or a preset `adsorbate` is required");
+        ELEMENT_TABLE.get_by_symbol(symbol)
     }
 
-    pub fn target_bondlength(&self) -> f64 {
+    pub fn target_bondlength(&self) -> Option<&TargetBondlength> {
+        self.target_bondlength.as_ref()
+    }
+
+    /// Target bondlength to the atoms of the element, falling back to the
+    /// default of the preset adsorbate.
+    pub fn bondlength_for(&self, symbol: ElementSymbol) -> Option<f64> {
         self.target_bondlength
+            .as_ref()
+            .and_then(|target| target.for_element(symbol))
+            .or_else(|| {
+                self.preset()
+                    .map(|preset| preset.default_bondlength(symbol))
+            })
     }
 
     pub fn export_dir(&self) -> &PathBuf {
@@ -79,10 +231,20 @@ impl TaskTable {
         &self.kpoint_quality
     }
 
+    /// The custom `kpoint_spacing`, or the one of the `kpoint_quality`.
+    pub fn kpoint_spacing(&self) -> f64 {
+        self.kpoint_spacing
+            .unwrap_or_else(|| self.kpoint_quality.kpoint_spacing())
+    }
+
     pub fn edft(&self) -> bool {
         self.edft
     }
 
+    pub fn run_mode(&self) -> RunMode {
+        self.run_mode
+    }
+
     pub fn potential_dir(&self) -> Option<&String> {
         self.potential_dir.as_ref()
     }
@@ -95,12 +257,157 @@ impl TaskTable {
     pub fn z_range(&self) -> FractionalCoordRange {
         FractionalCoordRange::new(self.z_range.0, self.z_range.1)
     }
+
+    pub fn periodicity(&self) -> Periodicity {
+        Periodicity::from(self.periodic)
+    }
+
+    pub fn keep_side(&self) -> KeepSide {
+        self.keep_side
+    }
+
+    pub fn surface_normal(&self) -> Option<[f64; 3]> {
+        self.surface_normal
+    }
+
+    pub fn adsorbate(&self) -> Option<&AdsorbateSpec> {
+        self.adsorbate.as_ref()
+    }
+
+    pub fn symmetry_tolerance(&self) -> Option<f64> {
+        self.symmetry_tolerance
+    }
+
+    pub fn fingerprint(&self) -> Option<FingerprintClustering> {
+        self.fingerprint
+    }
+
+    pub fn export_format(&self) -> Option<ExportFormat> {
+        self.export_format
+    }
+
+    pub fn extxyz(&self) -> bool {
+        self.extxyz
+    }
+
+    pub fn fix_atoms(&self) -> Option<FixAtoms> {
+        self.fix_atoms
+    }
+
+    /// Initial spin of the new atoms of the element, `None` when the atoms
+    /// start unpolarised.
+    pub fn initial_spin(&self, symbol: ElementSymbol) -> Option<f64> {
+        self.initial_spin
+            .as_ref()
+            .and_then(|initial_spin| initial_spin.for_element(symbol))
+    }
+
+    /// Hubbard U (eV) of the element, `None` without DFT+U.
+    pub fn hubbard_u(&self, symbol: ElementSymbol) -> Option<f64> {
+        self.hubbard_u
+            .as_ref()
+            .and_then(|hubbard_u| hubbard_u.for_element(symbol))
+    }
+
+    pub fn qe_cutoff(&self) -> QeCutoff {
+        self.qe_cutoff
+    }
+
+    pub fn cut_off_energy(&self) -> Option<f64> {
+        self.cut_off_energy
+    }
+
+    pub fn job_script(&self) -> Option<&JobScript> {
+        self.job_script.as_ref()
+    }
+
+    pub fn export_layout(&self) -> ExportLayout {
+        self.export_layout
+    }
+
+    pub fn preset(&self) -> Option<&'static AdsorbatePreset> {
+        self.adsorbate.as_ref().and_then(|spec| spec.preset())
+    }
+
+    /// The exclusion radius set for the element, if any.
+    pub fn exclusion_radius(&self, symbol: ElementSymbol) -> Option<f64> {
+        self.exclusion_radius
+            .as_ref()
+            .and_then(|table| table.get(&symbol).copied())
+    }
+}
+
+fn default_periodic() -> [bool; 3] {
+    Periodicity::default().axes()
 }
 
+/// Tolerance (Å) of the symmetry operations when the reduction is chosen interactively
+pub(crate) const DEFAULT_SYMMETRY_TOLERANCE: f64 = 0.1;
+
 #[cfg(test)]
 mod test {
+    use castep_periodic_table::element::ElementSymbol;
+
     use super::TaskTable;
 
+    #[test]
+    fn invalid_bondlength_range() {
+        let table = |range: &str| -> TaskTable {
+            serde_yaml::from_str(&format!(
+                "model_path: model.cell
+new_element: Pt
+target_bondlength: {range}
+x_range: [0.0, 1.0]
+y_range: [0.0, 1.0]
+z_range: [0.0, 1.0]
+export_dir: out
+potential_dir: ~
+kpoint_quality: Coarse
+edft: false
+"
+            ))
+            .unwrap()
+        };
+        assert!(table("[2.0, 2.4]").validate().is_ok());
+        assert!(table("[2.4, 2.0]").validate().is_err());
+        assert!(table("[-1.0, 2.0]").validate().is_err());
+    }
+
+    #[test]
+    fn opt_in_initial_spin() {
+        let table = |initial_spin: &str| -> TaskTable {
+            serde_yaml::from_str(&format!(
+                "model_path: model.cell
+new_element: Pt
+target_bondlength: 2.0
+x_range: [0.0, 1.0]
+y_range: [0.0, 1.0]
+z_range: [0.0, 1.0]
+export_dir: out
+potential_dir: ~
+kpoint_quality: Coarse
+edft: false
+{initial_spin}
+"
+            ))
+            .unwrap()
+        };
+        assert_eq!(table("").initial_spin(ElementSymbol::Pt), None);
+        assert_eq!(
+            table("initial_spin: false").initial_spin(ElementSymbol::Pt),
+            None
+        );
+        assert_eq!(
+            table("initial_spin: true").initial_spin(ElementSymbol::Pt),
+            Some(2.0)
+        );
+        let per_element = table("initial_spin: {Fe: 3.0, Pt: 0.0}");
+        assert_eq!(per_element.initial_spin(ElementSymbol::Fe), Some(3.0));
+        assert_eq!(per_element.initial_spin(ElementSymbol::Pt), None);
+        assert_eq!(per_element.initial_spin(ElementSymbol::Co), Some(3.0));
+        assert_eq!(per_element.initial_spin(ElementSymbol::O), None);
+    }
+
     #[test]
     fn test_task_table() {
         let table_path = "example_task.yaml";
@@ -0,0 +1,241 @@
+use std::{
+    collections::HashMap,
+    f64::consts::{PI, TAU},
+};
+
+use castep_periodic_table::element::ElementSymbol;
+use nalgebra::{Point3, Unit, UnitQuaternion, UnitVector3, Vector3};
+
+use crate::{
+    algorithm::has_closer_atoms, coordination_sites::coordinating_positions, SearchConfig,
+    SiteIndex,
+};
+
+/// A small molecule placed at the sites by its anchor atom, e.g. `O` of `OH`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Adsorbate {
+    symbols: Vec<ElementSymbol>,
+    /// Positions relative to the anchor atom
+    offsets: Vec<Vector3<f64>>,
+    anchor: usize,
+}
+
+/// Atoms of an adsorbate placed at a site, in cartesian coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdsorbatePose {
+    atoms: Vec<(ElementSymbol, Point3<f64>)>,
+    rotation_index: usize,
+}
+
+impl Adsorbate {
+    /// # Panics
+    /// Panics if the lengths of `symbols` and `coords` differ, or `anchor` is out of range.
+    pub fn new(symbols: Vec<ElementSymbol>, coords: &[Point3<f64>], anchor: usize) -> Self {
+        assert_eq!(symbols.len(), coords.len());
+        let anchor_coord = coords[anchor];
+        let offsets = coords.iter().map(|p| p - anchor_coord).collect();
+        Self {
+            symbols,
+            offsets,
+            anchor,
+        }
+    }
+
+    pub fn symbols(&self) -> &[ElementSymbol] {
+        self.symbols.as_ref()
+    }
+
+    pub fn anchor(&self) -> usize {
+        self.anchor
+    }
+
+    pub fn anchor_symbol(&self) -> ElementSymbol {
+        self.symbols[self.anchor]
+    }
+
+    /// From the anchor to the centre of the other atoms, `None` for single atoms.
+    fn axis(&self) -> Option<UnitVector3<f64>> {
+        if self.offsets.len() < 2 {
+            return None;
+        }
+        let centre: Vector3<f64> =
+            self.offsets.iter().sum::<Vector3<f64>>() / (self.offsets.len() - 1) as f64;
+        Unit::try_new(centre, 1e-6)
+    }
+
+    /// Rotations about the axis give the same pose for linear molecules, e.g. `CO`.
+    fn is_linear(&self, axis: &UnitVector3<f64>) -> bool {
+        self.offsets
+            .iter()
+            .all(|offset| offset.cross(&axis.into_inner()).norm() < 1e-3)
+    }
+
+    /// Place the anchor at the site, with the molecule pointing along the local
+    /// site normal, i.e. from the coordinating atoms to the site. `rotations`
+    /// evenly spaced turns about the normal are sampled, and the poses with any
+    /// atom closer to the substrate than the exclusion radii are rejected.
+    /// `exclusion_radii` holds the radii of the adsorbate atoms of each element
+    /// to the atoms of the model, indexed by the atom ids. The elements not
+    /// listed take the exclusion radii of the `search_config`.
+    pub fn poses_at(
+        &self,
+        point: Point3<f64>,
+        atom_ids: &[usize],
+        site_index: &SiteIndex,
+        search_config: &SearchConfig,
+        exclusion_radii: &HashMap<ElementSymbol, Vec<f64>>,
+        rotations: usize,
+    ) -> Vec<AdsorbatePose> {
+        let site_normal = site_normal(&point, atom_ids, site_index, search_config);
+        let (alignment, rotations) = match self.axis() {
+            Some(axis) => {
+                let alignment = UnitQuaternion::rotation_between_axis(&axis, &site_normal)
+                    .unwrap_or_else(|| {
+                        UnitQuaternion::from_axis_angle(&perpendicular_axis(&axis), PI)
+                    });
+                let rotations = if self.is_linear(&axis) {
+                    1
+                } else {
+                    rotations.max(1)
+                };
+                (alignment, rotations)
+            }
+            None => (UnitQuaternion::identity(), 1),
+        };
+        (0..rotations)
+            .filter_map(|i| {
+                let turn = UnitQuaternion::from_axis_angle(
+                    &site_normal,
+                    TAU * i as f64 / rotations as f64,
+                );
+                let rotation = turn * alignment;
+                let atoms: Vec<(ElementSymbol, Point3<f64>)> = self
+                    .symbols
+                    .iter()
+                    .zip(self.offsets.iter())
+                    .map(|(&symbol, offset)| (symbol, point + rotation * offset))
+                    .collect();
+                let clashed = atoms
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != self.anchor)
+                    .any(|(_, (symbol, p))| {
+                        let config = exclusion_radii
+                            .get(symbol)
+                            .map(|radii| search_config.with_exclusion_radii(radii))
+                            .unwrap_or(*search_config);
+                        has_closer_atoms(p, site_index, &config)
+                    });
+                (!clashed).then_some(AdsorbatePose {
+                    atoms,
+                    rotation_index: i,
+                })
+            })
+            .collect()
+    }
+}
+
+impl AdsorbatePose {
+    pub fn atoms(&self) -> &[(ElementSymbol, Point3<f64>)] {
+        self.atoms.as_ref()
+    }
+
+    /// Index of the sampled rotation about the site normal.
+    pub fn rotation_index(&self) -> usize {
+        self.rotation_index
+    }
+}
+
+/// From the centre of the coordinating atoms to the site, or the surface
+/// normal when the site sits at the centre, e.g. interstitials.
+fn site_normal(
+    point: &Point3<f64>,
+    atom_ids: &[usize],
+    site_index: &SiteIndex,
+    search_config: &SearchConfig,
+) -> UnitVector3<f64> {
+    let coordinating = coordinating_positions(point, atom_ids, site_index, search_config);
+    if coordinating.is_empty() {
+        return search_config.surface_normal();
+    }
+    let centre: Vector3<f64> =
+        coordinating.iter().map(|p| p.coords).sum::<Vector3<f64>>() / coordinating.len() as f64;
+    Unit::try_new(point.coords - centre, 1e-3).unwrap_or(search_config.surface_normal())
+}
+
+fn perpendicular_axis(axis: &UnitVector3<f64>) -> UnitVector3<f64> {
+    let trial = if axis.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    Unit::new_normalize(axis.cross(&trial))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use castep_periodic_table::element::ElementSymbol;
+    use nalgebra::Point3;
+
+    use crate::{SearchConfig, SiteIndex};
+
+    use super::Adsorbate;
+
+    fn oh() -> Adsorbate {
+        Adsorbate::new(
+            vec![ElementSymbol::O, ElementSymbol::H],
+            &[Point3::origin(), Point3::new(0.97, 0.0, 0.0)],
+            0,
+        )
+    }
+
+    #[test]
+    fn poses_along_site_normal() {
+        let points = [Point3::new(0.0, 0.0, 0.0)];
+        let to_check = [(0, points[0])];
+        let search_config = SearchConfig::new(&to_check, 2.0);
+        let site_index = SiteIndex::new(&points);
+        let site = Point3::new(0.0, 0.0, 2.0);
+        let radii = HashMap::new();
+        // Turns about the axis of a linear molecule give the same pose
+        let poses = oh().poses_at(site, &[0], &site_index, &search_config, &radii, 4);
+        assert_eq!(poses.len(), 1);
+        let atoms = poses[0].atoms();
+        assert_eq!(atoms[0], (ElementSymbol::O, site));
+        assert!((atoms[1].1 - Point3::new(0.0, 0.0, 2.97)).norm() < 1e-9);
+        let ooh = Adsorbate::new(
+            vec![ElementSymbol::O, ElementSymbol::O, ElementSymbol::H],
+            &[
+                Point3::origin(),
+                Point3::new(0.8, 0.0, 1.2),
+                Point3::new(1.7, 0.0, 0.9),
+            ],
+            0,
+        );
+        let poses = ooh.poses_at(site, &[0], &site_index, &search_config, &radii, 4);
+        assert_eq!(poses.len(), 4);
+        assert_eq!(poses[3].rotation_index(), 3);
+        assert!(poses.iter().all(|pose| pose.atoms()[0].1 == site));
+    }
+
+    #[test]
+    fn clash_by_element_radii() {
+        // An atom above the site, 1.03 Å from the H of OH
+        let points = [Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, 4.0)];
+        let to_check = [(0, points[0]), (1, points[1])];
+        let search_config = SearchConfig::new(&to_check, 2.0);
+        let site_index = SiteIndex::new(&points);
+        let site = Point3::new(0.0, 0.0, 2.0);
+        // The exclusion radii of the search, 2.0 Å, reject the pose
+        let poses = oh().poses_at(site, &[0], &site_index, &search_config, &HashMap::new(), 1);
+        assert!(poses.is_empty());
+        let radii = HashMap::from([(ElementSymbol::H, vec![0.8, 0.8])]);
+        let poses = oh().poses_at(site, &[0], &site_index, &search_config, &radii, 1);
+        assert_eq!(poses.len(), 1);
+        let radii = HashMap::from([(ElementSymbol::H, vec![0.8, 1.2])]);
+        let poses = oh().poses_at(site, &[0], &site_index, &search_config, &radii, 1);
+        assert!(poses.is_empty());
+    }
+}
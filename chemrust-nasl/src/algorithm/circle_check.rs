@@ -30,15 +30,12 @@ pub fn check_circles(
     site_index: &SiteIndex,
     search_config: &SearchConfig,
 ) -> CircleCheckResult {
-    let kdtree = site_index.coord_tree();
-    let points = site_index.coord_tree().source();
-    let dist = search_config.bondlength;
     let mut coord_circles: Vec<CoordCircle> = Vec::new();
     let mut coord_points: Vec<MultiCoordPoint> = Vec::new();
     let check_results: Vec<CoordResult> = unchecked_circles
         .par_iter()
         .filter_map(|circ| -> Option<CoordResult> {
-            circ.common_neighbours_intersect(kdtree, points, dist)
+            circ.common_neighbours_intersect(site_index, search_config)
         })
         .collect();
     check_results.into_iter().for_each(|result| match result {
@@ -1,7 +1,11 @@
-use std::{f64::consts::FRAC_PI_8, ops::ControlFlow};
+use std::{
+    f64::consts::{FRAC_PI_8, PI},
+    ops::ControlFlow,
+};
 
+use castep_periodic_table::element::ElementSymbol;
 use kd_tree::KdIndexTree;
-use nalgebra::{distance_squared, Point3, Vector3};
+use nalgebra::{distance_squared, Point3, UnitQuaternion, UnitVector3, Vector3};
 use rayon::prelude::*;
 
 use self::{
@@ -9,14 +13,23 @@ use self::{
     sphere_check::{sphere_check, SphereCheckResult},
 };
 
+pub use periodic::{PeriodicImages, Periodicity};
+pub use side::IntersectionSide;
+pub use slab::SlabAnalysis;
+pub use symmetry::{SymmetryAnalysis, SymmetryOperation};
+
 use crate::{
-    coordination_sites::{CoordCircle, MultiCoordPoint},
-    geometry::{approx_cmp_f64, FloatOrdering},
+    coordination_sites::{cluster_by_fingerprint, CoordCircle, MultiCoordPoint},
+    geometry::{approx_cmp_f64, approx_eq_point_f64, FloatEq, FloatOrdering},
     DelegatePoint, Visualize,
 };
 
 mod circle_check;
+mod periodic;
+mod side;
+mod slab;
 mod sphere_check;
+mod symmetry;
 #[cfg(test)]
 mod test;
 
@@ -24,6 +37,14 @@ mod test;
 pub struct SearchConfig<'a> {
     to_check: &'a [(usize, Point3<f64>)],
     bondlength: f64,
+    atom_bondlengths: Option<&'a [f64]>,
+    max_bondlength: f64,
+    /// Accepted deviation below and above the target bondlength
+    tolerance: (f64, f64),
+    exclusion_radii: Option<&'a [f64]>,
+    max_exclusion_radius: Option<f64>,
+    intersection_side: IntersectionSide,
+    surface_normal: UnitVector3<f64>,
 }
 
 impl<'a> SearchConfig<'a> {
@@ -31,6 +52,71 @@ impl<'a> SearchConfig<'a> {
         Self {
             to_check,
             bondlength,
+            atom_bondlengths: None,
+            max_bondlength: bondlength,
+            tolerance: (0.0, 0.0),
+            exclusion_radii: None,
+            max_exclusion_radius: None,
+            intersection_side: IntersectionSide::default(),
+            surface_normal: Vector3::z_axis(),
+        }
+    }
+
+    /// The "up" direction of the model, e.g. from `SlabAnalysis`. The sites
+    /// around a single atom are first looked for along it. Default to `+z`.
+    pub fn with_surface_normal(self, surface_normal: UnitVector3<f64>) -> Self {
+        Self {
+            surface_normal,
+            ..self
+        }
+    }
+
+    /// Which of the two points to keep when a circle cuts a sphere at two points.
+    /// Default to the one with larger z.
+    pub fn with_intersection_side(self, intersection_side: IntersectionSide) -> Self {
+        Self {
+            intersection_side,
+            ..self
+        }
+    }
+
+    /// Minimum approach distances of the new atom to each atom of the model,
+    /// indexed by the atom ids. A site closer to an atom than its exclusion
+    /// radius is rejected, independent of the target bondlengths.
+    /// Without them, the lower limit of the accepted bondlengths is used.
+    pub fn with_exclusion_radii(self, exclusion_radii: &'a [f64]) -> Self {
+        let max_exclusion_radius = exclusion_radii.iter().cloned().fold(0.0, f64::max);
+        Self {
+            exclusion_radii: Some(exclusion_radii),
+            max_exclusion_radius: Some(max_exclusion_radius),
+            ..self
+        }
+    }
+
+    /// Accept the distances from `min` to `max` as bonded, instead of exactly the `bondlength`.
+    /// The atom spheres are still built from the target bondlengths, and the window
+    /// shifts along with the per-atom target bondlengths.
+    /// # Panics
+    /// Panics if the `bondlength` is not in `[min, max]`
+    pub fn with_bondlength_range(self, min: f64, max: f64) -> Self {
+        assert!(min <= self.bondlength && self.bondlength <= max);
+        Self {
+            tolerance: (self.bondlength - min, max - self.bondlength),
+            ..self
+        }
+    }
+
+    /// Target bondlengths to each atom of the model, indexed by the atom ids.
+    /// Atoms not covered fall back to the `bondlength`.
+    pub fn with_atom_bondlengths(self, atom_bondlengths: &'a [f64]) -> Self {
+        let max_bondlength = atom_bondlengths
+            .iter()
+            .cloned()
+            .fold(self.bondlength, f64::max);
+        Self {
+            atom_bondlengths: Some(atom_bondlengths),
+            max_bondlength,
+            ..self
         }
     }
 
@@ -41,18 +127,102 @@ impl<'a> SearchConfig<'a> {
     pub fn bondlength(&self) -> f64 {
         self.bondlength
     }
+
+    /// Target bondlength between the new atom and the atom of `atom_id` in the model.
+    pub fn bondlength_of(&self, atom_id: usize) -> f64 {
+        self.atom_bondlengths
+            .and_then(|lengths| lengths.get(atom_id).copied())
+            .unwrap_or(self.bondlength)
+    }
+
+    /// Accepted bondlengths `(min, max)` between the new atom and the atom of `atom_id`.
+    pub fn bondlength_range_of(&self, atom_id: usize) -> (f64, f64) {
+        let bondlength = self.bondlength_of(atom_id);
+        (bondlength - self.tolerance.0, bondlength + self.tolerance.1)
+    }
+
+    /// Minimum approach distance of the new atom to the atom of `atom_id`.
+    pub fn exclusion_radius_of(&self, atom_id: usize) -> f64 {
+        self.exclusion_radii
+            .and_then(|radii| radii.get(atom_id).copied())
+            .unwrap_or_else(|| self.bondlength_range_of(atom_id).0)
+    }
+
+    /// The largest exclusion radius, which bounds the clash checks.
+    pub fn max_exclusion_radius(&self) -> f64 {
+        self.max_exclusion_radius.unwrap_or(self.max_bondlength)
+    }
+
+    pub fn intersection_side(&self) -> IntersectionSide {
+        self.intersection_side
+    }
+
+    pub fn surface_normal(&self) -> UnitVector3<f64> {
+        self.surface_normal
+    }
+
+    /// The largest target bondlength, which bounds the neighbour queries.
+    pub fn max_bondlength(&self) -> f64 {
+        self.max_bondlength
+    }
+
+    /// The largest accepted bondlength.
+    pub fn max_distance(&self) -> f64 {
+        self.max_bondlength + self.tolerance.1
+    }
+
+    /// Target bondlength to the indexed atom, which can be a periodic image.
+    pub(crate) fn bondlength_to(&self, site_index: &SiteIndex, id: usize) -> f64 {
+        self.bondlength_of(site_index.origin_id(id))
+    }
+
+    pub(crate) fn bondlength_range_to(&self, site_index: &SiteIndex, id: usize) -> (f64, f64) {
+        self.bondlength_range_of(site_index.origin_id(id))
+    }
+
+    pub(crate) fn exclusion_radius_to(&self, site_index: &SiteIndex, id: usize) -> f64 {
+        self.exclusion_radius_of(site_index.origin_id(id))
+    }
 }
 
-pub struct SiteIndex<'a>(KdIndexTree<'a, Point3<f64>>);
+pub struct SiteIndex<'a> {
+    coord_tree: KdIndexTree<'a, Point3<f64>>,
+    images: Option<&'a PeriodicImages>,
+}
 
 impl<'a> SiteIndex<'a> {
     pub fn new(coords: &'a [Point3<f64>]) -> Self {
         let coord_tree = KdIndexTree::build_by_ordered_float(coords);
-        Self(coord_tree)
+        Self {
+            coord_tree,
+            images: None,
+        }
+    }
+
+    /// Index the atoms together with their periodic images, so the neighbours
+    /// across the cell boundaries are visible to the search.
+    pub fn new_periodic(images: &'a PeriodicImages) -> Self {
+        let coord_tree = KdIndexTree::build_by_ordered_float(images.coords());
+        Self {
+            coord_tree,
+            images: Some(images),
+        }
     }
 
     pub fn coord_tree(&self) -> &KdIndexTree<'a, Point3<f64>> {
-        &self.0
+        &self.coord_tree
+    }
+
+    /// Index of the atom in the model that the indexed atom is an image of.
+    pub fn origin_id(&self, id: usize) -> usize {
+        self.images.map(|images| images.origin_id(id)).unwrap_or(id)
+    }
+
+    /// Translate the point back into the cell along the periodic directions.
+    pub fn wrap_point(&self, point: Point3<f64>) -> Point3<f64> {
+        self.images
+            .map(|images| images.wrap_point(point))
+            .unwrap_or(point)
     }
 }
 
@@ -88,6 +258,44 @@ impl SearchReports {
         self.viable_double_points.as_ref()
     }
 
+    /// Keep one site of each group of symmetry-equivalent sites, with the
+    /// size of the group as its multiplicity.
+    pub fn reduced_by_symmetry(&self, symmetry: &SymmetryAnalysis) -> SearchReports {
+        SearchReports::new(
+            self.points
+                .as_ref()
+                .map(|points| symmetry.reduce_sites(points)),
+            self.viable_single_points
+                .as_ref()
+                .map(|points| symmetry.reduce_sites(points)),
+            self.viable_double_points
+                .as_ref()
+                .map(|points| symmetry.reduce_sites(points)),
+        )
+    }
+
+    /// Keep one site of each cluster of sites with similar chemical
+    /// environments, see `cluster_by_fingerprint`.
+    pub fn clustered_by_fingerprint(
+        &self,
+        symbols: &[ElementSymbol],
+        site_index: &SiteIndex,
+        cutoff: f64,
+        threshold: f64,
+    ) -> SearchReports {
+        SearchReports::new(
+            self.points.as_ref().map(|points| {
+                cluster_by_fingerprint(points, symbols, site_index, cutoff, threshold)
+            }),
+            self.viable_single_points.as_ref().map(|points| {
+                cluster_by_fingerprint(points, symbols, site_index, cutoff, threshold)
+            }),
+            self.viable_double_points.as_ref().map(|points| {
+                cluster_by_fingerprint(points, symbols, site_index, cutoff, threshold)
+            }),
+        )
+    }
+
     pub fn validated_results<T: Visualize + Clone>(
         coord_sites: &[T],
         site_index: &SiteIndex,
@@ -107,12 +315,36 @@ pub fn search_sites(site_index: &SiteIndex, search_config: &SearchConfig) -> Sea
     let viable_single_sites = search_possible_single_points(site_index, search_config);
     let viable_double_sites = search_possible_double_points(
         sphere_intersect_results.unchecked_circles(),
-        site_index.coord_tree(),
-        search_config.bondlength(),
+        site_index,
+        search_config,
     );
     SearchReports::new(special_sites, viable_single_sites, viable_double_sites)
 }
 
+/// Map the point into the cell and its coordinating atoms to the atoms in the model.
+fn fold_multi_point(point: &MultiCoordPoint, site_index: &SiteIndex) -> MultiCoordPoint {
+    let mut atom_ids: Vec<usize> = point
+        .atom_ids()
+        .iter()
+        .map(|&id| site_index.origin_id(id))
+        .collect();
+    atom_ids.sort();
+    atom_ids.dedup();
+    MultiCoordPoint::new(site_index.wrap_point(point.point()), atom_ids)
+}
+
+fn fold_delegate_point<const N: usize>(
+    point: &DelegatePoint<N>,
+    site_index: &SiteIndex,
+) -> DelegatePoint<N> {
+    let mut atom_ids = point.atom_ids;
+    atom_ids
+        .iter_mut()
+        .for_each(|id| *id = site_index.origin_id(*id));
+    atom_ids.sort();
+    DelegatePoint::new(site_index.wrap_point(point.point()), atom_ids)
+}
+
 fn search_special_sites(
     sphere_intersect_results: &SphereCheckResult,
     site_index: &SiteIndex,
@@ -123,13 +355,23 @@ fn search_special_sites(
         site_index,
         search_config,
     );
-    let points = [
+    let points: Vec<MultiCoordPoint> = [
         sphere_intersect_results.single_points(),
         circle_check_results.points(),
     ]
-    .concat();
-    let dedup_points =
-        MultiCoordPoint::dedup_points(&points, site_index.coord_tree(), search_config.bondlength);
+    .concat()
+    .iter()
+    .map(|point| fold_multi_point(point, site_index))
+    .collect();
+    let dedup_points: Vec<MultiCoordPoint> =
+        MultiCoordPoint::dedup_points(&points, site_index, search_config)
+            .into_iter()
+            .map(|point| {
+                point
+                    .coordinate_within_window(site_index, search_config)
+                    .classified(site_index, search_config)
+            })
+            .collect();
     if !dedup_points.is_empty() {
         println!("Special multi-coordinated sites search completed.");
         Some(dedup_points)
@@ -146,8 +388,16 @@ fn search_possible_single_points(
         .to_check()
         .par_iter()
         .filter_map(|&(i, pt)| {
-            brute_force(pt, search_config.bondlength(), site_index.coord_tree())
-                .map(|coord| DelegatePoint::<1>::new(coord, [i]))
+            brute_force(
+                pt,
+                search_config.bondlength_of(i),
+                site_index,
+                search_config,
+            )
+            .map(|coord| {
+                DelegatePoint::<1>::new(site_index.wrap_point(coord), [i])
+                    .classified(site_index, search_config)
+            })
         })
         .collect();
     if !results.is_empty() {
@@ -159,12 +409,38 @@ fn search_possible_single_points(
 
 fn search_possible_double_points(
     unchecked_circles: &[CoordCircle],
-    kdtree: &KdIndexTree<Point3<f64>>,
-    dist: f64,
+    site_index: &SiteIndex,
+    search_config: &SearchConfig,
 ) -> Option<Vec<DelegatePoint<2>>> {
-    let results: Vec<DelegatePoint<2>> = unchecked_circles
+    // The centre of the circle, wrapped into the cell, tells the pair of atoms
+    // apart from the other images of the same two atoms.
+    let found: Vec<(Point3<f64>, DelegatePoint<2>)> = unchecked_circles
         .par_iter()
-        .filter_map(|circ| circ.get_possible_point(kdtree, dist))
+        .filter_map(|circ| {
+            circ.get_possible_point(site_index, search_config)
+                .map(|point| {
+                    (
+                        site_index.wrap_point(circ.circle().center()),
+                        fold_delegate_point(&point, site_index),
+                    )
+                })
+        })
+        .collect();
+    // The same pair of atoms is met from both of the atoms, and across the
+    // cell boundaries from their images.
+    let mut visited_centers: Vec<Point3<f64>> = Vec::new();
+    let results: Vec<DelegatePoint<2>> = found
+        .into_iter()
+        .filter(|(center, _)| {
+            let visited = visited_centers
+                .iter()
+                .any(|&c| matches!(approx_eq_point_f64(c, *center), FloatEq::Eq));
+            if !visited {
+                visited_centers.push(*center);
+            }
+            !visited
+        })
+        .map(|(_, point)| point.classified(site_index, search_config))
         .collect();
     if !results.is_empty() {
         Some(results)
@@ -176,7 +452,8 @@ fn search_possible_double_points(
 fn brute_force(
     origin: Point3<f64>,
     dist: f64,
-    kdtree: &KdIndexTree<Point3<f64>>,
+    site_index: &SiteIndex,
+    search_config: &SearchConfig,
 ) -> Option<Point3<f64>> {
     let step = FRAC_PI_8 / 2_f64;
     let azimuth: [f64; 32] = (0..32)
@@ -204,18 +481,19 @@ fn brute_force(
         })
         .collect::<Vec<Vec<Vector3<f64>>>>()
         .concat();
+    // The sampling above is around `+z`, turn it to the surface normal.
+    let rotation =
+        UnitQuaternion::rotation_between_axis(&Vector3::z_axis(), &search_config.surface_normal())
+            .unwrap_or_else(|| UnitQuaternion::from_axis_angle(&Vector3::x_axis(), PI));
     let initial = Vector3::z_axis().scale(dist);
-    let candidates = [[initial].to_vec(), positions].concat();
+    let candidates: Vec<Vector3<f64>> = [[initial].to_vec(), positions]
+        .concat()
+        .into_iter()
+        .map(|dir| rotation * dir)
+        .collect();
     let p = candidates.iter().try_for_each(|dir| {
         let p = origin + dir;
-        if kdtree
-            .within_radius(&p, dist + 10_f64 * f64::EPSILON)
-            .iter()
-            .any(|&&nb| {
-                let distance = distance_squared(&p, kdtree.item(nb));
-                matches!(approx_cmp_f64(distance, dist.powi(2)), FloatOrdering::Less)
-            })
-        {
+        if has_closer_atoms(&p, site_index, search_config) {
             ControlFlow::Continue(())
         } else {
             ControlFlow::Break(p)
@@ -232,20 +510,37 @@ pub fn validate_site<'a, 'b, T: Visualize>(
     site_index: &'b SiteIndex,
     search_config: &'b SearchConfig,
 ) -> Option<&'a T> {
-    let coord = coord_site.determine_coord();
-    let bondlength = search_config.bondlength;
-    let dist = bondlength.powi(2);
-    if site_index
-        .0
-        .within_radius(&coord, bondlength)
-        .iter()
-        .any(|&&nb| {
-            let distance = distance_squared(&coord, site_index.0.item(nb));
-            matches!(approx_cmp_f64(distance, dist), FloatOrdering::Less)
-        })
-    {
+    let coord = coord_site.determine_coord_along(&search_config.surface_normal());
+    if has_closer_atoms(&coord, site_index, search_config) {
         None
     } else {
         Some(coord_site)
     }
 }
+
+/// Whether any atom is closer to the point than its exclusion radius.
+pub(crate) fn has_closer_atoms(
+    point: &Point3<f64>,
+    site_index: &SiteIndex,
+    search_config: &SearchConfig,
+) -> bool {
+    let kdtree = site_index.coord_tree();
+    kdtree
+        .within_radius(point, search_config.max_exclusion_radius())
+        .iter()
+        .any(|&&nb| {
+            let distance = distance_squared(point, kdtree.item(nb));
+            let radius = search_config.exclusion_radius_to(site_index, nb);
+            matches!(
+                approx_cmp_f64(distance, radius.powi(2)),
+                FloatOrdering::Less
+            )
+        })
+}
+
+/// Whether the distance is within the accepted `(min, max)`, with the tolerance of `approx_cmp_f64`.
+pub(crate) fn within_window(distance: f64, window: (f64, f64)) -> bool {
+    let (min, max) = window;
+    !matches!(approx_cmp_f64(distance, min), FloatOrdering::Less)
+        && !matches!(approx_cmp_f64(distance, max), FloatOrdering::Greater)
+}
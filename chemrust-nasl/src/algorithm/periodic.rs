@@ -0,0 +1,171 @@
+use nalgebra::{Matrix3, Point3, Vector3};
+
+/// Whether the model repeats itself along the lattice vectors `a`, `b` and `c`.
+/// Default to periodic along all three, as a `.cell` model is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Periodicity([bool; 3]);
+
+impl Periodicity {
+    pub fn new(a: bool, b: bool, c: bool) -> Self {
+        Self([a, b, c])
+    }
+    /// Periodic along all three lattice vectors, e.g. bulk models.
+    pub fn bulk() -> Self {
+        Self([true; 3])
+    }
+    /// Periodic along `a` and `b`, e.g. slab models with vacuum along `c`.
+    pub fn slab() -> Self {
+        Self([true, true, false])
+    }
+    /// Not periodic at all, e.g. clusters.
+    pub fn none() -> Self {
+        Self([false; 3])
+    }
+    pub fn is_periodic(&self, axis: usize) -> bool {
+        self.0[axis]
+    }
+    pub fn axes(&self) -> [bool; 3] {
+        self.0
+    }
+}
+
+impl Default for Periodicity {
+    fn default() -> Self {
+        Self::bulk()
+    }
+}
+
+impl From<[bool; 3]> for Periodicity {
+    fn from(value: [bool; 3]) -> Self {
+        Self(value)
+    }
+}
+
+/// Cartesian coordinates of the atoms in the cell, followed by their periodic
+/// images within `cutoff` of the cell faces.
+/// # Note
+/// The original atoms keep their indices, so `origin_id` of an index smaller
+/// than the number of atoms is the index itself.
+#[derive(Debug, Clone)]
+pub struct PeriodicImages {
+    coords: Vec<Point3<f64>>,
+    origin_ids: Vec<usize>,
+    lattice_bases: Matrix3<f64>,
+    inverse_bases: Matrix3<f64>,
+    periodicity: Periodicity,
+}
+
+impl PeriodicImages {
+    /// The `cutoff` should be at least twice the largest bondlength in search,
+    /// so every atom that can touch a site of an in-cell atom is present.
+    pub fn new(
+        coords: &[Point3<f64>],
+        lattice_bases: Matrix3<f64>,
+        periodicity: Periodicity,
+        cutoff: f64,
+    ) -> Self {
+        let inverse_bases = lattice_bases
+            .try_inverse()
+            .expect("Matrix is not invertible");
+        let heights = cell_heights(&lattice_bases);
+        // The cutoff in fractional length along each direction, zero for non-periodic ones.
+        let frac_margin: [f64; 3] = [0, 1, 2].map(|axis| {
+            if periodicity.is_periodic(axis) {
+                cutoff / heights[axis]
+            } else {
+                0.0
+            }
+        });
+        let repeats: [i32; 3] = frac_margin.map(|m| m.ceil() as i32);
+        let shifts: Vec<Vector3<f64>> = (-repeats[0]..=repeats[0])
+            .flat_map(|i| {
+                (-repeats[1]..=repeats[1]).flat_map(move |j| {
+                    (-repeats[2]..=repeats[2])
+                        .map(move |k| Vector3::new(i as f64, j as f64, k as f64))
+                })
+            })
+            .filter(|shift| shift.norm_squared() > 0.0)
+            .collect();
+        let mut image_coords = coords.to_vec();
+        let mut origin_ids: Vec<usize> = (0..coords.len()).collect();
+        shifts.iter().for_each(|shift| {
+            coords.iter().enumerate().for_each(|(id, p)| {
+                let frac = inverse_bases * p + shift;
+                let within_margin = (0..3).all(|axis| {
+                    !periodicity.is_periodic(axis)
+                        || (frac[axis] >= -frac_margin[axis]
+                            && frac[axis] <= 1.0 + frac_margin[axis])
+                });
+                if within_margin {
+                    image_coords.push(lattice_bases * frac);
+                    origin_ids.push(id);
+                }
+            })
+        });
+        Self {
+            coords: image_coords,
+            origin_ids,
+            lattice_bases,
+            inverse_bases,
+            periodicity,
+        }
+    }
+
+    pub fn coords(&self) -> &[Point3<f64>] {
+        self.coords.as_ref()
+    }
+
+    pub fn origin_id(&self, id: usize) -> usize {
+        self.origin_ids[id]
+    }
+
+    /// Translate the point back into the cell along the periodic directions.
+    pub fn wrap_point(&self, point: Point3<f64>) -> Point3<f64> {
+        let mut frac = self.inverse_bases * point;
+        (0..3)
+            .filter(|&axis| self.periodicity.is_periodic(axis))
+            .for_each(|axis| {
+                let v = frac[axis] - frac[axis].floor();
+                // Snap the far face to the near face, so the same site will not
+                // be reported at both `0.0` and `1.0`.
+                frac[axis] = if 1.0 - v < 1e-8 { 0.0 } else { v };
+            });
+        self.lattice_bases * frac
+    }
+
+    pub fn lattice_bases(&self) -> Matrix3<f64> {
+        self.lattice_bases
+    }
+
+    pub fn periodicity(&self) -> Periodicity {
+        self.periodicity
+    }
+}
+
+/// Distances between the opposite faces of the cell, along the directions of `a`, `b`, `c`.
+fn cell_heights(lattice_bases: &Matrix3<f64>) -> [f64; 3] {
+    let volume = lattice_bases.determinant().abs();
+    let a: Vector3<f64> = lattice_bases.column(0).into_owned();
+    let b: Vector3<f64> = lattice_bases.column(1).into_owned();
+    let c: Vector3<f64> = lattice_bases.column(2).into_owned();
+    [b.cross(&c).norm(), c.cross(&a).norm(), a.cross(&b).norm()].map(|area| volume / area)
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{Matrix3, Point3};
+
+    use super::{PeriodicImages, Periodicity};
+
+    #[test]
+    fn images_and_wrapping() {
+        let lattice = Matrix3::new(4.0, 0.0, 0.0, 0.0, 4.0, 0.0, 0.0, 0.0, 10.0);
+        let coords = [Point3::new(0.5, 2.0, 5.0)];
+        let images = PeriodicImages::new(&coords, lattice, Periodicity::slab(), 1.0);
+        // Only the image shifted by +a falls within 1.0 Å of the cell faces
+        assert_eq!(images.coords().len(), 2);
+        assert_eq!(images.origin_id(1), 0);
+        let wrapped = images.wrap_point(Point3::new(-0.5, 2.0, 11.0));
+        assert!((wrapped - Point3::new(3.5, 2.0, 11.0)).norm() < 1e-8);
+    }
+}
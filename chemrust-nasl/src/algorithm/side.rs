@@ -0,0 +1,64 @@
+use nalgebra::{distance_squared, Point3, UnitVector3, Vector3};
+
+use crate::geometry::{approx_cmp_f64, FloatOrdering};
+
+/// Which point to keep when a coordination circle cuts an atom sphere at two points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IntersectionSide {
+    /// Keep both points, e.g. for adsorption on both faces of 2D materials.
+    Both,
+    /// Keep the point farther along the normal, e.g. the surface normal of a slab.
+    Normal(UnitVector3<f64>),
+    /// Keep the point farther from the centre, e.g. the centre of mass of a bulk model.
+    AwayFrom(Point3<f64>),
+}
+
+impl Default for IntersectionSide {
+    fn default() -> Self {
+        Self::Normal(Vector3::z_axis())
+    }
+}
+
+impl IntersectionSide {
+    pub fn select(&self, p1: Point3<f64>, p2: Point3<f64>) -> Vec<Point3<f64>> {
+        let (v1, v2) = match self {
+            IntersectionSide::Both => return vec![p1, p2],
+            IntersectionSide::Normal(n) => (n.dot(&p1.coords), n.dot(&p2.coords)),
+            IntersectionSide::AwayFrom(centre) => {
+                (distance_squared(&p1, centre), distance_squared(&p2, centre))
+            }
+        };
+        if let FloatOrdering::Greater = approx_cmp_f64(v1, v2) {
+            vec![p1]
+        } else {
+            vec![p2]
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{Point3, Unit, Vector3};
+
+    use super::IntersectionSide;
+
+    #[test]
+    fn select_by_side() {
+        let p1 = Point3::new(1.0, 0.0, 2.0);
+        let p2 = Point3::new(1.0, 0.0, -2.0);
+        assert_eq!(IntersectionSide::Both.select(p1, p2), vec![p1, p2]);
+        assert_eq!(IntersectionSide::default().select(p1, p2), vec![p1]);
+        assert_eq!(IntersectionSide::default().select(p2, p1), vec![p1]);
+        let down = Unit::new_normalize(Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(IntersectionSide::Normal(down).select(p1, p2), vec![p2]);
+        // A normal along `x` sees no difference, and keeps the second point
+        assert_eq!(
+            IntersectionSide::Normal(Vector3::x_axis()).select(p1, p2),
+            vec![p2]
+        );
+        let centre = Point3::new(0.0, 0.0, -5.0);
+        assert_eq!(IntersectionSide::AwayFrom(centre).select(p1, p2), vec![p1]);
+        let centre = Point3::new(0.0, 0.0, 5.0);
+        assert_eq!(IntersectionSide::AwayFrom(centre).select(p1, p2), vec![p2]);
+    }
+}
@@ -0,0 +1,135 @@
+use nalgebra::{Matrix3, Point3, Unit, UnitVector3, Vector3};
+
+use crate::geometry::Plane;
+
+/// The vacuum of a periodic slab model, found by scanning the atom positions
+/// along each lattice vector for the largest empty gap.
+#[derive(Debug, Clone, Copy)]
+pub struct SlabAnalysis {
+    vacuum_axis: usize,
+    vacuum_gap: f64,
+    normal: UnitVector3<f64>,
+    top: Plane,
+    bottom: Plane,
+}
+
+impl SlabAnalysis {
+    /// # Returns
+    /// `None` when no gap along the lattice vectors reaches `min_gap` (Å),
+    /// e.g. bulk models.
+    pub fn new(coords: &[Point3<f64>], lattice_bases: Matrix3<f64>, min_gap: f64) -> Option<Self> {
+        let inverse_bases = lattice_bases.try_inverse()?;
+        let volume = lattice_bases.determinant().abs();
+        let (vacuum_axis, vacuum_gap, top_frac, bottom_frac) = (0..3)
+            .filter_map(|axis| {
+                let (next, prev) = ((axis + 1) % 3, (axis + 2) % 3);
+                let face_area = lattice_bases
+                    .column(next)
+                    .cross(&lattice_bases.column(prev))
+                    .norm();
+                let height = volume / face_area;
+                let mut frac: Vec<f64> = coords
+                    .iter()
+                    .map(|p| {
+                        let v = (inverse_bases * p)[axis];
+                        v - v.floor()
+                    })
+                    .collect();
+                frac.sort_by(|a, b| a.total_cmp(b));
+                let (top, bottom, gap) = largest_gap(&frac)?;
+                Some((axis, gap * height, top, bottom))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        if vacuum_gap < min_gap {
+            return None;
+        }
+        let (next, prev) = ((vacuum_axis + 1) % 3, (vacuum_axis + 2) % 3);
+        let axis_vector: Vector3<f64> = lattice_bases.column(vacuum_axis).into_owned();
+        let face_normal: Vector3<f64> = lattice_bases
+            .column(next)
+            .cross(&lattice_bases.column(prev));
+        // Point the normal out of the top surface, towards the vacuum.
+        let normal = if face_normal.dot(&axis_vector) < 0.0 {
+            Unit::new_normalize(-face_normal)
+        } else {
+            Unit::new_normalize(face_normal)
+        };
+        let top = Plane::from_normal_and_point(normal, Point3::from(axis_vector * top_frac));
+        let bottom = Plane::from_normal_and_point(normal, Point3::from(axis_vector * bottom_frac));
+        Some(Self {
+            vacuum_axis,
+            vacuum_gap,
+            normal,
+            top,
+            bottom,
+        })
+    }
+
+    /// Index of the lattice vector crossing the vacuum, `0, 1, 2` for `a, b, c`.
+    pub fn vacuum_axis(&self) -> usize {
+        self.vacuum_axis
+    }
+
+    /// Thickness of the vacuum in Å.
+    pub fn vacuum_gap(&self) -> f64 {
+        self.vacuum_gap
+    }
+
+    /// Surface normal pointing from the top surface into the vacuum.
+    pub fn normal(&self) -> UnitVector3<f64> {
+        self.normal
+    }
+
+    /// Plane through the outermost atoms on the side of the normal.
+    pub fn top(&self) -> Plane {
+        self.top
+    }
+
+    /// Plane through the outermost atoms on the other side, sharing the normal of the top.
+    /// # Note
+    /// It can lie below the cell origin, when the slab crosses the cell boundary.
+    pub fn bottom(&self) -> Plane {
+        self.bottom
+    }
+
+    /// Signed distance of the point above the top surface, along the normal.
+    pub fn height_above_top(&self, point: &Point3<f64>) -> f64 {
+        self.normal.dot(&point.coords) + self.top.d()
+    }
+}
+
+/// The largest gap between the sorted fractional coordinates, taking the wrap
+/// around from `1.0` to `0.0`, as `(top, bottom, gap)` of the slab.
+/// The `bottom` is shifted below `top` when the slab crosses the cell boundary.
+fn largest_gap(sorted_frac: &[f64]) -> Option<(f64, f64, f64)> {
+    let first = *sorted_frac.first()?;
+    let last = *sorted_frac.last()?;
+    sorted_frac
+        .windows(2)
+        .map(|pair| (pair[0], pair[1] - 1.0, pair[1] - pair[0]))
+        .chain([(last, first, first + 1.0 - last)])
+        .max_by(|a, b| a.2.total_cmp(&b.2))
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{Matrix3, Point3};
+
+    use super::SlabAnalysis;
+
+    #[test]
+    fn vacuum_along_c() {
+        let lattice = Matrix3::new(4.0, 0.0, 0.0, 0.0, 4.0, 0.0, 0.0, 0.0, 20.0);
+        let coords = [
+            Point3::new(0.0, 0.0, 1.0),
+            Point3::new(2.0, 2.0, 3.0),
+            Point3::new(0.0, 0.0, 5.0),
+        ];
+        let slab = SlabAnalysis::new(&coords, lattice, 5.0).expect("vacuum along c");
+        assert_eq!(slab.vacuum_axis(), 2);
+        assert!((slab.vacuum_gap() - 16.0).abs() < 1e-8);
+        assert!((slab.normal().z - 1.0).abs() < 1e-8);
+        assert!((slab.height_above_top(&Point3::new(0.0, 0.0, 7.0)) - 2.0).abs() < 1e-8);
+        assert!((slab.bottom().d() + 1.0).abs() < 1e-8);
+    }
+}
@@ -1,6 +1,5 @@
 use std::collections::HashSet;
 
-use kd_tree::KdIndexTree;
 use nalgebra::Point3;
 
 use crate::geometry::{Intersect, Sphere, SphereSphereResult};
@@ -64,19 +63,14 @@ fn sphere_check_fn(
     site_index: &SiteIndex,
     search_config: &SearchConfig,
 ) -> CoordResult {
-    let sphere = Sphere::new(query, search_config.bondlength());
+    let radius = search_config.bondlength_of(atom_id);
+    let sphere = Sphere::new(query, radius);
     let tree = site_index.coord_tree();
-    let neighbours = tree.within_radius(&query, search_config.bondlength() * 2.0);
+    let neighbours = tree.within_radius(&query, radius + search_config.max_bondlength());
     if neighbours.len() == 1 {
         CoordResult::Empty
     } else {
-        sphere_neighbour_check(
-            &sphere,
-            atom_id,
-            &neighbours,
-            site_index.coord_tree(),
-            search_config.bondlength(),
-        )
+        sphere_neighbour_check(&sphere, atom_id, &neighbours, site_index, search_config)
     }
 }
 
@@ -84,9 +78,10 @@ fn sphere_neighbour_check(
     sphere: &Sphere,
     atom_id: usize,
     neighbours: &[&usize],
-    coord_tree: &KdIndexTree<Point3<f64>>,
-    dist: f64,
+    site_index: &SiteIndex,
+    search_config: &SearchConfig,
 ) -> CoordResult {
+    let coord_tree = site_index.coord_tree();
     let mut visited_pair: HashSet<[usize; 2]> = HashSet::new();
     let sphere_neighbor_results: Vec<CoordResult> = neighbours
         .iter()
@@ -95,13 +90,16 @@ fn sphere_neighbour_check(
             let mut id_pair = [atom_id, nb_id];
             id_pair.sort();
             if visited_pair.insert(id_pair) {
-                let nb_sphere = Sphere::new(*coord_tree.item(nb_id), dist);
+                let nb_sphere = Sphere::new(
+                    *coord_tree.item(nb_id),
+                    search_config.bondlength_to(site_index, nb_id),
+                );
                 match sphere.intersect(&nb_sphere) {
                     SphereSphereResult::Empty => None,
                     SphereSphereResult::Point(p) => {
                         let coord_point = MultiCoordPoint::new(p, id_pair.to_vec());
                         coord_point
-                            .no_closer_atoms(coord_tree, dist)
+                            .no_closer_atoms(site_index, search_config)
                             .map(CoordResult::SinglePoint)
                     }
                     SphereSphereResult::Circle(c) => {
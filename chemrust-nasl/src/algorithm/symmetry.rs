@@ -0,0 +1,320 @@
+use castep_periodic_table::element::ElementSymbol;
+use nalgebra::{Matrix3, Point3, UnitVector3, Vector3};
+
+use crate::{CoordSite, Visualize};
+
+use super::Periodicity;
+
+/// Relative tolerance of the metric tensor kept by the lattice rotations.
+const METRIC_TOLERANCE: f64 = 1e-3;
+/// Tolerance of the surface normal kept by the rotations.
+const NORMAL_TOLERANCE: f64 = 1e-3;
+
+/// A symmetry operation `x' = W x + t` in fractional coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SymmetryOperation {
+    rotation: Matrix3<f64>,
+    translation: Vector3<f64>,
+}
+
+impl SymmetryOperation {
+    /// The integer matrix `W` acting on the fractional coordinates.
+    pub fn rotation(&self) -> Matrix3<f64> {
+        self.rotation
+    }
+
+    pub fn translation(&self) -> Vector3<f64> {
+        self.translation
+    }
+
+    pub fn apply(&self, frac_coord: &Point3<f64>) -> Point3<f64> {
+        self.rotation * frac_coord + self.translation
+    }
+}
+
+/// Space group operations of the model within a distance tolerance, found by
+/// mapping the atoms onto the atoms of the same element.
+#[derive(Debug, Clone)]
+pub struct SymmetryAnalysis {
+    operations: Vec<SymmetryOperation>,
+    lattice_bases: Matrix3<f64>,
+    inverse_bases: Matrix3<f64>,
+    periodicity: Periodicity,
+    surface_normal: Option<UnitVector3<f64>>,
+    tolerance: f64,
+}
+
+impl SymmetryAnalysis {
+    /// The `coords` are cartesian, in the order of `symbols`. With a
+    /// `surface_normal`, only the operations keeping it are taken, so the
+    /// sites above a slab are not mapped onto the ones below.
+    /// `tolerance` (Å) is the largest displacement of the atoms by an operation.
+    pub fn new(
+        coords: &[Point3<f64>],
+        symbols: &[ElementSymbol],
+        lattice_bases: Matrix3<f64>,
+        periodicity: Periodicity,
+        surface_normal: Option<UnitVector3<f64>>,
+        tolerance: f64,
+    ) -> Self {
+        let inverse_bases = lattice_bases
+            .try_inverse()
+            .expect("Matrix is not invertible");
+        let mut analysis = Self {
+            operations: vec![SymmetryOperation {
+                rotation: Matrix3::identity(),
+                translation: Vector3::zeros(),
+            }],
+            lattice_bases,
+            inverse_bases,
+            periodicity,
+            surface_normal,
+            tolerance,
+        };
+        let frac_coords: Vec<Point3<f64>> = coords.iter().map(|p| inverse_bases * p).collect();
+        // Candidate translations map an atom of the rarest element onto the
+        // atoms of the same element.
+        let Some(reference) = (0..symbols.len()).min_by_key(|&i| {
+            symbols
+                .iter()
+                .filter(|&&symbol| symbol == symbols[i])
+                .count()
+        }) else {
+            return analysis;
+        };
+        lattice_rotations(&lattice_bases, periodicity, surface_normal)
+            .into_iter()
+            .for_each(|rotation| {
+                let image = rotation * frac_coords[reference];
+                (0..symbols.len())
+                    .filter(|&j| symbols[j] == symbols[reference])
+                    .for_each(|j| {
+                        let mut translation = frac_coords[j] - image;
+                        (0..3)
+                            .filter(|&axis| periodicity.is_periodic(axis))
+                            .for_each(|axis| {
+                                translation[axis] -= translation[axis].floor();
+                            });
+                        let operation = SymmetryOperation {
+                            rotation,
+                            translation,
+                        };
+                        if !analysis.contains(&operation)
+                            && analysis.maps_onto_itself(&operation, &frac_coords, symbols)
+                        {
+                            analysis.operations.push(operation);
+                        }
+                    })
+            });
+        analysis
+    }
+
+    pub fn operations(&self) -> &[SymmetryOperation] {
+        self.operations.as_ref()
+    }
+
+    /// Number of distinct rotations, i.e. the order of the point group.
+    pub fn point_group_order(&self) -> usize {
+        let mut rotations: Vec<Matrix3<f64>> = Vec::new();
+        self.operations.iter().for_each(|op| {
+            if !rotations.contains(&op.rotation) {
+                rotations.push(op.rotation);
+            }
+        });
+        rotations.len()
+    }
+
+    /// Group the sites into orbits of the operations, and keep one site of each
+    /// orbit with the size of the orbit as its multiplicity. Sites of different
+    /// kinds or coordination numbers are never grouped together.
+    /// The sites are placed with the surface normal as the "up" direction, if any.
+    /// # Note
+    /// The kept site is the first one within the cell along the non-periodic
+    /// directions, if any.
+    pub fn reduce_sites<T: CoordSite + Visualize + Clone>(&self, sites: &[T]) -> Vec<T> {
+        let frac_coords: Vec<Point3<f64>> = sites
+            .iter()
+            .map(|site| {
+                let coord = match self.surface_normal {
+                    Some(normal) => site.determine_coord_along(&normal),
+                    None => site.determine_coord(),
+                };
+                self.inverse_bases * coord
+            })
+            .collect();
+        let mut visited = vec![false; sites.len()];
+        let mut orbits: Vec<Vec<usize>> = Vec::new();
+        for i in 0..sites.len() {
+            if visited[i] {
+                continue;
+            }
+            visited[i] = true;
+            let images: Vec<Point3<f64>> = self
+                .operations
+                .iter()
+                .map(|op| op.apply(&frac_coords[i]))
+                .collect();
+            let mut orbit = vec![i];
+            for j in (i + 1)..sites.len() {
+                if !visited[j]
+                    && same_label(&sites[i], &sites[j])
+                    && images
+                        .iter()
+                        .any(|image| self.frac_distance(image, &frac_coords[j]) < self.tolerance)
+                {
+                    visited[j] = true;
+                    orbit.push(j);
+                }
+            }
+            orbits.push(orbit);
+        }
+        orbits
+            .iter()
+            .map(|orbit| {
+                let kept = orbit
+                    .iter()
+                    .find(|&&k| self.within_cell(&frac_coords[k]))
+                    .unwrap_or(&orbit[0]);
+                sites[*kept].clone().with_multiplicity(orbit.len())
+            })
+            .collect()
+    }
+
+    fn contains(&self, operation: &SymmetryOperation) -> bool {
+        self.operations.iter().any(|op| {
+            op.rotation == operation.rotation
+                && self.frac_distance(
+                    &Point3::from(op.translation),
+                    &Point3::from(operation.translation),
+                ) < self.tolerance
+        })
+    }
+
+    fn maps_onto_itself(
+        &self,
+        operation: &SymmetryOperation,
+        frac_coords: &[Point3<f64>],
+        symbols: &[ElementSymbol],
+    ) -> bool {
+        frac_coords.iter().zip(symbols.iter()).all(|(p, symbol)| {
+            let image = operation.apply(p);
+            frac_coords
+                .iter()
+                .zip(symbols.iter())
+                .any(|(q, s)| s == symbol && self.frac_distance(&image, q) < self.tolerance)
+        })
+    }
+
+    /// Cartesian distance between the fractional coordinates, up to the
+    /// lattice translations along the periodic directions.
+    fn frac_distance(&self, a: &Point3<f64>, b: &Point3<f64>) -> f64 {
+        let mut diff = a - b;
+        (0..3)
+            .filter(|&axis| self.periodicity.is_periodic(axis))
+            .for_each(|axis| diff[axis] -= diff[axis].round());
+        (self.lattice_bases * diff).norm()
+    }
+
+    fn within_cell(&self, frac_coord: &Point3<f64>) -> bool {
+        (0..3).all(|axis| {
+            self.periodicity.is_periodic(axis) || (-1e-8..=1.0 + 1e-8).contains(&frac_coord[axis])
+        })
+    }
+}
+
+/// Integer matrices with entries of `-1, 0, 1` keeping the metric tensor of
+/// the lattice, the non-periodic directions, and the surface normal if any.
+fn lattice_rotations(
+    lattice_bases: &Matrix3<f64>,
+    periodicity: Periodicity,
+    surface_normal: Option<UnitVector3<f64>>,
+) -> Vec<Matrix3<f64>> {
+    let metric = lattice_bases.transpose() * lattice_bases;
+    let scale = metric.diagonal().max();
+    let inverse_bases = lattice_bases
+        .try_inverse()
+        .expect("Matrix is not invertible");
+    (0..3_usize.pow(9))
+        .map(|code| {
+            Matrix3::from_iterator((0..9).map(|k| ((code / 3_usize.pow(k)) % 3) as f64 - 1.0))
+        })
+        .filter(|w| (w.determinant().abs() - 1.0).abs() < 1e-8)
+        .filter(|w| (w.transpose() * metric * w - metric).amax() < METRIC_TOLERANCE * scale)
+        .filter(|w| {
+            (0..3)
+                .filter(|&axis| !periodicity.is_periodic(axis))
+                .all(|axis| {
+                    (0..3).all(|k| k == axis || (w[(axis, k)] == 0.0 && w[(k, axis)] == 0.0))
+                })
+        })
+        .filter(|w| match surface_normal {
+            Some(normal) => {
+                let cartesian = lattice_bases * w * inverse_bases;
+                (cartesian * normal.into_inner() - normal.into_inner()).norm() < NORMAL_TOLERANCE
+            }
+            None => true,
+        })
+        .collect()
+}
+
+fn same_label<T: CoordSite>(a: &T, b: &T) -> bool {
+    a.coordinating_atoms().len() == b.coordinating_atoms().len() && a.site_kind() == b.site_kind()
+}
+
+#[cfg(test)]
+mod test {
+    use castep_periodic_table::element::ElementSymbol;
+    use nalgebra::{Matrix3, Point3, Vector3};
+
+    use crate::{geometry::Sphere, CoordSphere, MultiCoordPoint, Periodicity};
+
+    use super::SymmetryAnalysis;
+
+    #[test]
+    fn square_lattice_orbits() {
+        let lattice = Matrix3::new(4.0, 0.0, 0.0, 0.0, 4.0, 0.0, 0.0, 0.0, 20.0);
+        let coords = [Point3::new(0.0, 0.0, 5.0)];
+        let symmetry = SymmetryAnalysis::new(
+            &coords,
+            &[ElementSymbol::Pt],
+            lattice,
+            Periodicity::bulk(),
+            Some(Vector3::z_axis()),
+            0.1,
+        );
+        // 4mm of the square lattice, without the mirror of the slab
+        assert_eq!(symmetry.operations().len(), 8);
+        assert_eq!(symmetry.point_group_order(), 8);
+        let sites = [
+            MultiCoordPoint::new(Point3::new(2.0, 0.0, 6.0), vec![0]),
+            MultiCoordPoint::new(Point3::new(0.0, 2.0, 6.0), vec![0]),
+            MultiCoordPoint::new(Point3::new(2.0, 2.0, 6.0), vec![0]),
+        ];
+        let reduced = symmetry.reduce_sites(&sites);
+        assert_eq!(reduced.len(), 2);
+        assert_eq!(reduced[0].multiplicity(), 2);
+        assert_eq!(reduced[1].multiplicity(), 1);
+    }
+
+    #[test]
+    fn sites_along_non_z_normal() {
+        // A slab with the vacuum along `a`
+        let lattice = Matrix3::new(20.0, 0.0, 0.0, 0.0, 4.0, 0.0, 0.0, 0.0, 4.0);
+        let coords = [Point3::new(5.0, 0.0, 0.0)];
+        let symmetry = SymmetryAnalysis::new(
+            &coords,
+            &[ElementSymbol::Pt],
+            lattice,
+            Periodicity::new(false, true, true),
+            Some(Vector3::x_axis()),
+            0.1,
+        );
+        // Placed along `+z`, the two sites would both sit at `z = 2.0` above the atom
+        let sites = [
+            CoordSphere::new(Sphere::new(coords[0], 2.0), 0),
+            CoordSphere::new(Sphere::new(coords[0], 6.0), 0),
+        ];
+        let reduced = symmetry.reduce_sites(&sites);
+        assert_eq!(reduced.len(), 2);
+    }
+}
@@ -7,9 +7,11 @@ use chemrust_core::data::geom::coordinates::CoordData;
 use chemrust_core::data::lattice::CrystalModel;
 use chemrust_core::data::lattice::UnitCellParameters;
 use kd_tree::KdIndexTree;
-use nalgebra::{Point3, Vector3};
+use nalgebra::{distance, Matrix3, Point3, Vector3};
 
-use crate::{search_sites, SearchConfig, SearchReports, SiteIndex};
+use crate::{search_sites, PeriodicImages, Periodicity, SearchConfig, SearchReports, SiteIndex};
+
+use super::within_window;
 
 fn load_model(model_rel_path: &str) -> Result<CellDocument, CellParseError> {
     let root_dir = env!("CARGO_MANIFEST_DIR");
@@ -50,3 +52,61 @@ fn test_search() {
     dbg!(results.viable_double_points());
     dbg!(results.points());
 }
+
+#[test]
+fn per_atom_bondlengths() {
+    let points = [Point3::new(0.0, 0.0, 0.0), Point3::new(3.0, 0.0, 0.0)];
+    let search_points: Vec<(usize, Point3<f64>)> =
+        points.iter().enumerate().map(|(i, p)| (i, *p)).collect();
+    let bondlengths = [2.0, 1.5];
+    let search_config = SearchConfig::new(&search_points, 1.8).with_atom_bondlengths(&bondlengths);
+    assert_eq!(search_config.bondlength_of(1), 1.5);
+    // Atoms beyond the table fall back to the `bondlength`
+    assert_eq!(search_config.bondlength_of(2), 1.8);
+    assert_eq!(search_config.max_bondlength(), 2.0);
+    let site_index = SiteIndex::new(&points);
+    let results = search_sites(&site_index, &search_config);
+    let double_points = results.viable_double_points().unwrap();
+    assert_eq!(double_points.len(), 1);
+    let site = double_points[0].point();
+    assert!((distance(&site, &points[0]) - 2.0).abs() < 1e-6);
+    assert!((distance(&site, &points[1]) - 1.5).abs() < 1e-6);
+}
+
+#[test]
+fn periodic_images_of_a_pair_are_distinct_sites() {
+    // A chain along `a`, with the pairs 0-1 (1.2 Å) and 1-0' (1.8 Å) in each cell
+    let lattice = Matrix3::new(3.0, 0.0, 0.0, 0.0, 20.0, 0.0, 0.0, 0.0, 20.0);
+    let points = [Point3::new(0.0, 10.0, 10.0), Point3::new(1.2, 10.0, 10.0)];
+    let images = PeriodicImages::new(&points, lattice, Periodicity::bulk(), 3.0);
+    let site_index = SiteIndex::new_periodic(&images);
+    let search_points: Vec<(usize, Point3<f64>)> =
+        points.iter().enumerate().map(|(i, p)| (i, *p)).collect();
+    let search_config = SearchConfig::new(&search_points, 1.5);
+    let results = search_sites(&site_index, &search_config);
+    let double_points = results.viable_double_points().unwrap();
+    assert_eq!(double_points.len(), 2);
+    let mut xs: Vec<f64> = double_points.iter().map(|p| p.point().x).collect();
+    xs.sort_by(|a, b| a.total_cmp(b));
+    assert!((xs[0] - 0.6).abs() < 1e-6);
+    assert!((xs[1] - 2.1).abs() < 1e-6);
+}
+
+#[test]
+fn bondlength_window() {
+    let points = [Point3::new(0.0, 0.0, 0.0), Point3::new(3.0, 0.0, 0.0)];
+    let search_points: Vec<(usize, Point3<f64>)> =
+        points.iter().enumerate().map(|(i, p)| (i, *p)).collect();
+    let bondlengths = [2.0, 2.4];
+    let search_config = SearchConfig::new(&search_points, 2.2)
+        .with_atom_bondlengths(&bondlengths)
+        .with_bondlength_range(2.1, 2.5);
+    // The window of (-0.1, +0.3) shifts with the bondlength of each atom
+    let window = search_config.bondlength_range_of(0);
+    assert!((window.0 - 1.9).abs() < 1e-12 && (window.1 - 2.3).abs() < 1e-12);
+    assert!((search_config.bondlength_range_of(1).1 - 2.7).abs() < 1e-12);
+    assert!(within_window(1.9, window));
+    assert!(within_window(2.3 + 5e-6, window));
+    assert!(!within_window(1.89, window));
+    assert!(!within_window(2.31, window));
+}
@@ -4,14 +4,12 @@ use std::{
     ops::ControlFlow,
 };
 
-use kd_tree::KdIndexTree;
-use nalgebra::{distance_squared, Point3};
+use nalgebra::{distance, Point3};
 
 use crate::{
-    geometry::{
-        approx_cmp_f64, Circle3d, CircleSphereIntersection, FloatOrdering, Intersect, Sphere,
-    },
-    CoordResult, DelegatePoint, MultiCoordPoint,
+    algorithm::{has_closer_atoms, within_window},
+    geometry::{Circle3d, CircleSphereIntersection, Intersect, Sphere},
+    CoordResult, DelegatePoint, IntersectionSide, MultiCoordPoint, SearchConfig, SiteIndex,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -27,22 +25,27 @@ impl CoordCircle {
 
     pub fn get_possible_point(
         &self,
-        coord_tree: &KdIndexTree<Point3<f64>>,
-        dist: f64,
+        site_index: &SiteIndex,
+        search_config: &SearchConfig,
     ) -> Option<DelegatePoint<2>> {
+        let coord_tree = site_index.coord_tree();
         let step_frac_pi_32 = FRAC_PI_8 / 4.0;
         let possible_position: Vec<f64> = (0..32)
             .map(|i| FRAC_PI_2 + i as f64 * step_frac_pi_32)
             .collect();
         let p = possible_position.iter().try_for_each(|&theta| {
             let query = self.circle().get_point_on_circle(theta);
-            let neighbours = coord_tree.within_radius(&query, dist + 1e-5_f64);
-            if !neighbours.iter().any(|&&nb| {
-                let found = coord_tree.item(nb);
-                let distance = distance_squared(&query, found);
-                matches!(approx_cmp_f64(distance, dist.powi(2)), FloatOrdering::Less)
-            }) {
-                if neighbours.len() <= 2 {
+            if !has_closer_atoms(&query, site_index, search_config) {
+                // Atoms within their accepted bondlength range from the point
+                let bonded = coord_tree
+                    .within_radius(&query, search_config.max_distance() + 1e-5_f64)
+                    .iter()
+                    .filter(|&&&nb| {
+                        let d = distance(&query, coord_tree.item(nb));
+                        within_window(d, search_config.bondlength_range_to(site_index, nb))
+                    })
+                    .count();
+                if bonded <= 2 {
                     ControlFlow::Break(query)
                 } else {
                     ControlFlow::Continue(())
@@ -59,17 +62,19 @@ impl CoordCircle {
 
     fn get_common_neighbours(
         &self,
-        kdtree: &KdIndexTree<Point3<f64>>,
-        points: &[Point3<f64>],
-        dist: f64,
+        site_index: &SiteIndex,
+        search_config: &SearchConfig,
     ) -> HashSet<usize> {
+        let kdtree = site_index.coord_tree();
+        let points = kdtree.source();
         let each_neighbors: Vec<Vec<usize>> = self
             .atom_ids
             .iter()
             .map(|&i| {
                 let query: [f64; 3] = points[i].into();
+                let dist = search_config.bondlength_to(site_index, i);
                 kdtree
-                    .within_radius(&query, 2.0 * (dist + 1e-5_f64))
+                    .within_radius(&query, dist + search_config.max_bondlength() + 1e-5_f64)
                     .iter()
                     .skip(1)
                     .map(|&&i| i)
@@ -100,13 +105,11 @@ impl CoordCircle {
             Some(CoordResult::Circle(*self))
         } else {
             let coord_points: Vec<MultiCoordPoint> = neighbour_results
-                .iter()
-                .filter_map(|res| {
-                    if let CoordResult::SinglePoint(coord_point) = res {
-                        Some(coord_point.clone())
-                    } else {
-                        None
-                    }
+                .into_iter()
+                .flat_map(|res| match res {
+                    CoordResult::SinglePoint(coord_point) => vec![coord_point],
+                    CoordResult::Points(coord_points) => coord_points,
+                    _ => Vec::new(),
                 })
                 .collect();
             Some(CoordResult::Points(coord_points))
@@ -117,20 +120,27 @@ impl CoordCircle {
     /// `Some` for 1. `CoordResult::Circle`2. `CoordResult::Points`
     pub(crate) fn common_neighbours_intersect(
         &self,
-        kdtree: &KdIndexTree<Point3<f64>>,
-        points: &[Point3<f64>],
-        dist: f64,
+        site_index: &SiteIndex,
+        search_config: &SearchConfig,
     ) -> Option<CoordResult> {
+        let points = site_index.coord_tree().source();
         // Only common neighbors of the associated atoms are possible to
         // form further connections
-        let common_neighbors: HashSet<usize> = self.get_common_neighbours(kdtree, points, dist);
+        let common_neighbors: HashSet<usize> =
+            self.get_common_neighbours(site_index, search_config);
         let neighbor_results: Vec<CoordResult> = common_neighbors
             .iter()
             .map(|&i| {
                 let p = points[i];
                 // circle-sphere intersection
-                let sphere = Sphere::new(p, dist);
-                let circle_sphere = self.circle.intersect(&sphere);
+                let sphere = Sphere::new(p, search_config.bondlength_to(site_index, i));
+                let circle_sphere = match self.circle.intersect(&sphere) {
+                    CircleSphereIntersection::Zero => self
+                        .point_within_window(p, search_config.bondlength_range_to(site_index, i))
+                        .map(CircleSphereIntersection::Single)
+                        .unwrap_or(CircleSphereIntersection::Zero),
+                    res => res,
+                };
 
                 // #[cfg(debug_assertions)]
                 // {
@@ -153,12 +163,22 @@ impl CoordCircle {
                 //         dbg!(circle_sphere);
                 //     }
                 // }
-                circle_sphere.to_coord_result(&self.atom_ids, i)
+                circle_sphere.to_coord_result(&self.atom_ids, i, search_config.intersection_side())
             })
             .collect();
         self.classify_neighbour_results(neighbor_results)
     }
 
+    /// When the circle misses the sphere of the target bondlength, the point
+    /// on the circle nearest to or farthest from the atom may still be within
+    /// the accepted bondlength range.
+    fn point_within_window(&self, atom: Point3<f64>, window: (f64, f64)) -> Option<Point3<f64>> {
+        let (nearest, farthest) = self.circle.nearest_and_farthest_points(atom)?;
+        [nearest, farthest]
+            .into_iter()
+            .find(|p| within_window(distance(p, &atom), window))
+    }
+
     pub fn atom_ids(&self) -> [usize; 2] {
         self.atom_ids
     }
@@ -169,7 +189,12 @@ impl CoordCircle {
 }
 
 impl CircleSphereIntersection {
-    pub fn to_coord_result(self, circle_id: &[usize; 2], sphere_id: usize) -> CoordResult {
+    pub fn to_coord_result(
+        self,
+        circle_id: &[usize; 2],
+        sphere_id: usize,
+        side: IntersectionSide,
+    ) -> CoordResult {
         match self {
             CircleSphereIntersection::Zero => CoordResult::Empty,
             CircleSphereIntersection::Single(p) => {
@@ -178,18 +203,21 @@ impl CircleSphereIntersection {
                 CoordResult::SinglePoint(MultiCoordPoint::new(p, atom_id))
             }
             CircleSphereIntersection::Double(p1, p2) => {
-                let p = if let FloatOrdering::Greater = approx_cmp_f64(p1.z, p2.z) {
-                    p1
-                } else {
-                    p2
-                };
                 let mut atom_id = [circle_id[0], circle_id[1], sphere_id].to_vec();
                 atom_id.sort();
-                CoordResult::SinglePoint(MultiCoordPoint::new(p, atom_id))
+                let mut points: Vec<MultiCoordPoint> = side
+                    .select(p1, p2)
+                    .into_iter()
+                    .map(|p| MultiCoordPoint::new(p, atom_id.clone()))
+                    .collect();
+                if points.len() == 1 {
+                    CoordResult::SinglePoint(points.remove(0))
+                } else {
+                    CoordResult::Points(points)
+                }
             }
-            // Actually impossible in our current case that every sphere
-            // has the same radius, and thus there can't be a circle has the
-            // same radius as the sphere
+            // Only possible when the sphere centers on the axis of the circle,
+            // and every point of the circle is at the same distance to the atom.
             CircleSphereIntersection::Circle(_) => CoordResult::Invalid,
             CircleSphereIntersection::InsideSphere => CoordResult::Invalid,
             CircleSphereIntersection::SphereInCircle => CoordResult::Invalid,
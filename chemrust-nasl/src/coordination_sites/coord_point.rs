@@ -1,19 +1,36 @@
-use std::{collections::HashSet, ops::ControlFlow};
+use std::collections::HashSet;
 
-use kd_tree::KdIndexTree;
-use nalgebra::{distance_squared, Point3};
+use nalgebra::{distance, Point3};
 
-use crate::geometry::{approx_cmp_f64, approx_eq_point_f64, FloatEq, FloatOrdering};
+use crate::{
+    algorithm::{has_closer_atoms, within_window},
+    geometry::{approx_eq_point_f64, FloatEq},
+    SearchConfig, SiteIndex,
+};
+
+use super::site_kind::{classify_site, SiteKind};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MultiCoordPoint {
     pub(crate) point: Point3<f64>,
     pub(crate) atom_ids: Vec<usize>,
+    /// Distances to the coordinating atoms, in the order of `atom_ids`.
+    /// Only filled in the search results.
+    pub(crate) distances: Vec<f64>,
+    pub(crate) site_kind: Option<SiteKind>,
+    /// Number of symmetry-equivalent sites this one stands for.
+    pub(crate) multiplicity: usize,
 }
 
 impl MultiCoordPoint {
     pub fn new(point: Point3<f64>, atom_ids: Vec<usize>) -> Self {
-        Self { point, atom_ids }
+        Self {
+            point,
+            atom_ids,
+            distances: Vec::new(),
+            site_kind: None,
+            multiplicity: 1,
+        }
     }
     pub(crate) fn merge_with(&self, rhs: &Self) -> Option<MultiCoordPoint> {
         if let FloatEq::Eq = approx_eq_point_f64(self.point, rhs.point) {
@@ -31,30 +48,54 @@ impl MultiCoordPoint {
 
     pub fn no_closer_atoms(
         self,
-        kdtree: &KdIndexTree<Point3<f64>>,
-        dist: f64,
+        site_index: &SiteIndex,
+        search_config: &SearchConfig,
     ) -> Option<MultiCoordPoint> {
-        let closer_than_dist = kdtree
-            .within_radius(&self.point(), dist)
-            .iter()
-            .try_for_each(|&&nb| {
-                let distance = distance_squared(&self.point(), kdtree.item(nb));
-                if let FloatOrdering::Less = approx_cmp_f64(distance, dist.powi(2)) {
-                    ControlFlow::Break(nb)
-                } else {
-                    ControlFlow::Continue(())
-                }
-            });
-        if !matches!(closer_than_dist, ControlFlow::Break(_)) {
+        if !has_closer_atoms(&self.point(), site_index, search_config) {
             Some(self)
         } else {
             None
         }
     }
+    /// Take every atom within its accepted bondlength range as a coordinating atom,
+    /// and record the actual distances.
+    pub(crate) fn coordinate_within_window(
+        self,
+        site_index: &SiteIndex,
+        search_config: &SearchConfig,
+    ) -> MultiCoordPoint {
+        let kdtree = site_index.coord_tree();
+        let mut coordinated: Vec<(usize, f64)> = kdtree
+            .within_radius(&self.point, search_config.max_distance() + 1e-5_f64)
+            .iter()
+            .filter_map(|&&nb| {
+                let d = distance(&self.point, kdtree.item(nb));
+                within_window(d, search_config.bondlength_range_to(site_index, nb))
+                    .then_some((site_index.origin_id(nb), d))
+            })
+            .collect();
+        coordinated.sort_by(|a, b| a.0.cmp(&b.0));
+        let (atom_ids, distances): (Vec<usize>, Vec<f64>) = coordinated.into_iter().unzip();
+        Self {
+            point: self.point,
+            atom_ids,
+            distances,
+            site_kind: self.site_kind,
+            multiplicity: self.multiplicity,
+        }
+    }
+    /// Label the site by its coordinating atoms and the surface normal.
+    pub(crate) fn classified(self, site_index: &SiteIndex, search_config: &SearchConfig) -> Self {
+        let site_kind = classify_site(&self.point, &self.atom_ids, site_index, search_config);
+        Self {
+            site_kind: Some(site_kind),
+            ..self
+        }
+    }
     pub fn dedup_points(
         points: &[MultiCoordPoint],
-        kdtree: &KdIndexTree<Point3<f64>>,
-        dist: f64,
+        site_index: &SiteIndex,
+        search_config: &SearchConfig,
     ) -> Vec<MultiCoordPoint> {
         let mut visited = vec![false; points.len()];
         points
@@ -63,7 +104,7 @@ impl MultiCoordPoint {
             .filter_map(|(now, curr_p)| {
                 Self::look_for_same_points((now, curr_p), points, &mut visited)
             })
-            .filter_map(|p| p.no_closer_atoms(kdtree, dist))
+            .filter_map(|p| p.no_closer_atoms(site_index, search_config))
             .collect()
     }
     fn look_for_same_points(
@@ -110,17 +151,58 @@ impl MultiCoordPoint {
     pub fn point(&self) -> Point3<f64> {
         self.point
     }
+
+    pub fn distances(&self) -> &[f64] {
+        self.distances.as_ref()
+    }
+
+    /// Only labelled in the search results.
+    pub fn site_kind(&self) -> Option<SiteKind> {
+        self.site_kind
+    }
+
+    /// `1` unless reduced by symmetry.
+    pub fn multiplicity(&self) -> usize {
+        self.multiplicity
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct DelegatePoint<const N: usize> {
     pub(crate) point: Point3<f64>,
     pub(crate) atom_ids: [usize; N],
+    pub(crate) site_kind: Option<SiteKind>,
+    /// Number of symmetry-equivalent sites this one stands for.
+    pub(crate) multiplicity: usize,
 }
 
 impl<const N: usize> DelegatePoint<N> {
     pub fn new(point: Point3<f64>, atom_ids: [usize; N]) -> Self {
-        Self { point, atom_ids }
+        Self {
+            point,
+            atom_ids,
+            site_kind: None,
+            multiplicity: 1,
+        }
+    }
+
+    /// Label the site by its coordinating atoms and the surface normal.
+    pub(crate) fn classified(self, site_index: &SiteIndex, search_config: &SearchConfig) -> Self {
+        let site_kind = classify_site(&self.point, &self.atom_ids, site_index, search_config);
+        Self {
+            site_kind: Some(site_kind),
+            ..self
+        }
+    }
+
+    /// Only labelled in the search results.
+    pub fn site_kind(&self) -> Option<SiteKind> {
+        self.site_kind
+    }
+
+    /// `1` unless reduced by symmetry.
+    pub fn multiplicity(&self) -> usize {
+        self.multiplicity
     }
 
     pub fn point(&self) -> Point3<f64> {
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use castep_periodic_table::element::ElementSymbol;
+use nalgebra::{distance, Point3};
+
+use crate::SiteIndex;
+
+use super::{CoordSite, Visualize};
+
+/// Chemical environment of a site: the elements of the coordinating atoms,
+/// and the sorted distances to the neighbours within a cutoff, by element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteFingerprint {
+    coordinating: HashMap<ElementSymbol, usize>,
+    shells: HashMap<ElementSymbol, Vec<f64>>,
+    cutoff: f64,
+}
+
+impl SiteFingerprint {
+    /// The `symbols` are of the atoms in the model, indexed by the atom ids.
+    pub fn new(
+        point: &Point3<f64>,
+        atom_ids: &[usize],
+        symbols: &[ElementSymbol],
+        site_index: &SiteIndex,
+        cutoff: f64,
+    ) -> Self {
+        let mut coordinating: HashMap<ElementSymbol, usize> = HashMap::new();
+        atom_ids
+            .iter()
+            .for_each(|&id| *coordinating.entry(symbols[id]).or_default() += 1);
+        let kdtree = site_index.coord_tree();
+        let mut shells: HashMap<ElementSymbol, Vec<f64>> = HashMap::new();
+        kdtree.within_radius(point, cutoff).iter().for_each(|&&nb| {
+            let symbol = symbols[site_index.origin_id(nb)];
+            shells
+                .entry(symbol)
+                .or_default()
+                .push(distance(point, kdtree.item(nb)));
+        });
+        shells
+            .values_mut()
+            .for_each(|distances| distances.sort_by(|a, b| a.total_cmp(b)));
+        Self {
+            coordinating,
+            shells,
+            cutoff,
+        }
+    }
+
+    /// Largest difference (Å) between the matching neighbour distances of the
+    /// two sites. A neighbour without a match counts by how far it lies
+    /// within the cutoff, so the atoms near the cutoff hardly matter.
+    /// # Returns
+    /// `f64::INFINITY` when the coordinating elements differ.
+    pub fn difference(&self, other: &Self) -> f64 {
+        if self.coordinating != other.coordinating {
+            return f64::INFINITY;
+        }
+        let cutoff = self.cutoff.min(other.cutoff);
+        let empty = Vec::new();
+        self.shells
+            .keys()
+            .chain(other.shells.keys())
+            .map(|symbol| {
+                let lhs = self.shells.get(symbol).unwrap_or(&empty);
+                let rhs = other.shells.get(symbol).unwrap_or(&empty);
+                let (shorter, longer) = if lhs.len() <= rhs.len() {
+                    (lhs, rhs)
+                } else {
+                    (rhs, lhs)
+                };
+                let matched = shorter
+                    .iter()
+                    .zip(longer.iter())
+                    .map(|(a, b)| (a - b).abs())
+                    .fold(0.0, f64::max);
+                let unmatched = longer[shorter.len()..]
+                    .iter()
+                    .map(|d| (cutoff - d).max(0.0))
+                    .fold(0.0, f64::max);
+                matched.max(unmatched)
+            })
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Cluster the sites by their fingerprints: a site joins the first cluster
+/// whose first site differs from it by less than `threshold` (Å), and has the
+/// same kind. One site of each cluster is kept, standing for all of them.
+pub fn cluster_by_fingerprint<T: CoordSite + Visualize + Clone>(
+    sites: &[T],
+    symbols: &[ElementSymbol],
+    site_index: &SiteIndex,
+    cutoff: f64,
+    threshold: f64,
+) -> Vec<T> {
+    let fingerprints: Vec<SiteFingerprint> = sites
+        .iter()
+        .map(|site| {
+            SiteFingerprint::new(
+                &site.determine_coord(),
+                site.coordinating_atoms(),
+                symbols,
+                site_index,
+                cutoff,
+            )
+        })
+        .collect();
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    (0..sites.len()).for_each(|i| {
+        let joined = clusters.iter_mut().find(|cluster| {
+            let first = cluster[0];
+            sites[first].site_kind() == sites[i].site_kind()
+                && fingerprints[first].difference(&fingerprints[i]) < threshold
+        });
+        match joined {
+            Some(cluster) => cluster.push(i),
+            None => clusters.push(vec![i]),
+        }
+    });
+    clusters
+        .iter()
+        .map(|cluster| {
+            let multiplicity = cluster.iter().map(|&k| sites[k].multiplicity()).sum();
+            sites[cluster[0]].clone().with_multiplicity(multiplicity)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use castep_periodic_table::element::ElementSymbol;
+    use nalgebra::Point3;
+
+    use crate::SiteIndex;
+
+    use super::SiteFingerprint;
+
+    #[test]
+    fn distorted_sites_match() {
+        let coords = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+            Point3::new(10.0, 0.0, 0.0),
+            Point3::new(12.05, 0.0, 0.0),
+        ];
+        let symbols = [ElementSymbol::Pt; 4];
+        let site_index = SiteIndex::new(&coords);
+        let a = SiteFingerprint::new(
+            &Point3::new(1.0, 0.0, 1.5),
+            &[0, 1],
+            &symbols,
+            &site_index,
+            4.0,
+        );
+        let b = SiteFingerprint::new(
+            &Point3::new(11.0, 0.0, 1.5),
+            &[2, 3],
+            &symbols,
+            &site_index,
+            4.0,
+        );
+        assert!(a.difference(&b) < 0.1);
+        let top = SiteFingerprint::new(
+            &Point3::new(0.0, 0.0, 2.0),
+            &[0],
+            &symbols,
+            &site_index,
+            4.0,
+        );
+        assert_eq!(a.difference(&top), f64::INFINITY);
+    }
+}
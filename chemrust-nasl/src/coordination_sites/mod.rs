@@ -1,16 +1,38 @@
 mod coord_circle;
 mod coord_point;
 mod coord_sphere;
+mod fingerprint;
+mod site_kind;
 mod visualize;
 
 pub use coord_circle::CoordCircle;
 pub use coord_point::{DelegatePoint, MultiCoordPoint};
 pub use coord_sphere::CoordSphere;
+pub use fingerprint::{cluster_by_fingerprint, SiteFingerprint};
+pub(crate) use site_kind::coordinating_positions;
+pub use site_kind::SiteKind;
 pub use visualize::*;
 
 pub trait CoordSite {
     fn connecting_atoms_msg(&self) -> String;
     fn site_type(&self) -> String;
+    /// Ids of the atoms in the model coordinating the site.
+    fn coordinating_atoms(&self) -> &[usize];
+    /// Chemical label of the site, when it has been classified.
+    fn site_kind(&self) -> Option<SiteKind> {
+        None
+    }
+    /// Number of symmetry-equivalent sites represented by this one.
+    fn multiplicity(&self) -> usize {
+        1
+    }
+    /// Stand for `multiplicity` symmetry-equivalent sites.
+    fn with_multiplicity(self, _multiplicity: usize) -> Self
+    where
+        Self: Sized,
+    {
+        self
+    }
 }
 
 impl CoordSite for CoordCircle {
@@ -25,6 +47,10 @@ impl CoordSite for CoordCircle {
         )
     }
 
+    fn coordinating_atoms(&self) -> &[usize] {
+        &self.atom_ids
+    }
+
     fn site_type(&self) -> String {
         "double".to_string()
     }
@@ -35,6 +61,10 @@ impl CoordSite for CoordSphere {
         format!("single_{}", self.atom_id)
     }
 
+    fn coordinating_atoms(&self) -> &[usize] {
+        std::slice::from_ref(&self.atom_id)
+    }
+
     fn site_type(&self) -> String {
         "single".to_string()
     }
@@ -53,9 +83,28 @@ impl CoordSite for MultiCoordPoint {
         )
     }
 
+    fn coordinating_atoms(&self) -> &[usize] {
+        self.atom_ids()
+    }
+
     fn site_type(&self) -> String {
         "multi".to_string()
     }
+
+    fn site_kind(&self) -> Option<SiteKind> {
+        MultiCoordPoint::site_kind(self)
+    }
+
+    fn multiplicity(&self) -> usize {
+        MultiCoordPoint::multiplicity(self)
+    }
+
+    fn with_multiplicity(self, multiplicity: usize) -> Self {
+        Self {
+            multiplicity,
+            ..self
+        }
+    }
 }
 
 impl CoordSite for DelegatePoint<1> {
@@ -63,9 +112,28 @@ impl CoordSite for DelegatePoint<1> {
         format!("single_{}", self.atom_ids[0])
     }
 
+    fn coordinating_atoms(&self) -> &[usize] {
+        &self.atom_ids
+    }
+
     fn site_type(&self) -> String {
         "single".to_string()
     }
+
+    fn site_kind(&self) -> Option<SiteKind> {
+        DelegatePoint::site_kind(self)
+    }
+
+    fn multiplicity(&self) -> usize {
+        DelegatePoint::multiplicity(self)
+    }
+
+    fn with_multiplicity(self, multiplicity: usize) -> Self {
+        Self {
+            multiplicity,
+            ..self
+        }
+    }
 }
 
 impl CoordSite for DelegatePoint<2> {
@@ -73,9 +141,28 @@ impl CoordSite for DelegatePoint<2> {
         format!("double_{}_{}", self.atom_ids[0], self.atom_ids[1])
     }
 
+    fn coordinating_atoms(&self) -> &[usize] {
+        &self.atom_ids
+    }
+
     fn site_type(&self) -> String {
         "double".to_string()
     }
+
+    fn site_kind(&self) -> Option<SiteKind> {
+        DelegatePoint::site_kind(self)
+    }
+
+    fn multiplicity(&self) -> usize {
+        DelegatePoint::multiplicity(self)
+    }
+
+    fn with_multiplicity(self, multiplicity: usize) -> Self {
+        Self {
+            multiplicity,
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
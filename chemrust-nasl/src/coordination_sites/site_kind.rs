@@ -0,0 +1,218 @@
+use std::fmt::Display;
+
+use nalgebra::{distance_squared, Point3, Unit, UnitVector3};
+
+use crate::{IntersectionSide, SearchConfig, SiteIndex};
+
+/// Coordinating atoms higher than the site by this much (Å) along the surface
+/// normal make the site subsurface.
+const SUBSURFACE_TOLERANCE: f64 = 0.1;
+/// Atoms within this height (Å) are taken as the same layer.
+const LAYER_TOLERANCE: f64 = 0.5;
+/// An atom laterally within this distance (Å) from the site is directly below it.
+const BELOW_TOLERANCE: f64 = 0.5;
+
+/// Chemical label of an adsorption site
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SiteKind {
+    Top,
+    Bridge,
+    /// Threefold hollow without an atom directly below in the second layer
+    HollowFcc,
+    /// Threefold hollow above an atom of the second layer
+    HollowHcp,
+    Fourfold,
+    /// Sites coordinated by more than four atoms
+    Multifold(usize),
+    /// Sites below the coordinating atoms, e.g. interstitials
+    Subsurface,
+}
+
+impl Display for SiteKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SiteKind::Top => write!(f, "top"),
+            SiteKind::Bridge => write!(f, "bridge"),
+            SiteKind::HollowFcc => write!(f, "hollow_fcc"),
+            SiteKind::HollowHcp => write!(f, "hollow_hcp"),
+            SiteKind::Fourfold => write!(f, "fourfold"),
+            SiteKind::Multifold(n) => write!(f, "{n}fold"),
+            SiteKind::Subsurface => write!(f, "subsurface"),
+        }
+    }
+}
+
+/// Classify the site by the number of coordinating atoms, its height to them
+/// along the "up" direction of the side it was kept on, and the layer below them.
+pub(crate) fn classify_site(
+    point: &Point3<f64>,
+    atom_ids: &[usize],
+    site_index: &SiteIndex,
+    search_config: &SearchConfig,
+) -> SiteKind {
+    let kdtree = site_index.coord_tree();
+    let coordinating = coordinating_positions(point, atom_ids, site_index, search_config);
+    let normal = site_normal(point, &coordinating, search_config);
+    let height = |p: &Point3<f64>| normal.dot(&p.coords);
+    let site_height = height(point);
+    if coordinating
+        .iter()
+        .any(|p| height(p) > site_height + SUBSURFACE_TOLERANCE)
+    {
+        return SiteKind::Subsurface;
+    }
+    match coordinating.len() {
+        0 | 1 => SiteKind::Top,
+        2 => SiteKind::Bridge,
+        3 => {
+            let first_layer = coordinating
+                .iter()
+                .map(height)
+                .fold(f64::INFINITY, f64::min);
+            let nearby = kdtree.within_radius(point, 2.0 * search_config.max_distance());
+            let below: Vec<&Point3<f64>> = nearby
+                .iter()
+                .map(|&&nb| kdtree.item(nb))
+                .filter(|p| height(*p) < first_layer - LAYER_TOLERANCE)
+                .collect();
+            let second_layer = below
+                .iter()
+                .map(|p| height(*p))
+                .fold(f64::NEG_INFINITY, f64::max);
+            let atom_below = below.iter().any(|p| {
+                let offset = *p - point;
+                let lateral = offset - normal.scale(normal.dot(&offset));
+                height(*p) > second_layer - LAYER_TOLERANCE && lateral.norm() < BELOW_TOLERANCE
+            });
+            if atom_below {
+                SiteKind::HollowHcp
+            } else {
+                SiteKind::HollowFcc
+            }
+        }
+        4 => SiteKind::Fourfold,
+        n => SiteKind::Multifold(n),
+    }
+}
+
+/// The "up" direction of the site by the kept side of the search: the normal
+/// of `IntersectionSide::Normal`, away from the centre of
+/// `IntersectionSide::AwayFrom`, and with `IntersectionSide::Both`, the
+/// surface normal turned to the face of the coordinating atoms the site is on.
+fn site_normal(
+    point: &Point3<f64>,
+    coordinating: &[Point3<f64>],
+    search_config: &SearchConfig,
+) -> UnitVector3<f64> {
+    let surface_normal = search_config.surface_normal();
+    match search_config.intersection_side() {
+        IntersectionSide::Normal(normal) => normal,
+        IntersectionSide::AwayFrom(centre) => {
+            Unit::try_new(point - centre, 1e-8).unwrap_or(surface_normal)
+        }
+        IntersectionSide::Both => {
+            let site_height = surface_normal.dot(&point.coords);
+            let site_below = coordinating
+                .iter()
+                .map(|p| surface_normal.dot(&p.coords) - site_height)
+                .sum::<f64>()
+                > 0.0;
+            if site_below {
+                -surface_normal
+            } else {
+                surface_normal
+            }
+        }
+    }
+}
+
+/// The nearest positions of the coordinating atoms to the point, which can be periodic images.
+pub(crate) fn coordinating_positions(
+    point: &Point3<f64>,
+    atom_ids: &[usize],
+    site_index: &SiteIndex,
+    search_config: &SearchConfig,
+) -> Vec<Point3<f64>> {
+    let kdtree = site_index.coord_tree();
+    let nearby = kdtree.within_radius(point, search_config.max_distance() + 1e-5_f64);
+    atom_ids
+        .iter()
+        .filter_map(|&id| {
+            nearby
+                .iter()
+                .filter(|&&&nb| site_index.origin_id(nb) == id)
+                .map(|&&nb| *kdtree.item(nb))
+                .min_by(|a, b| distance_squared(a, point).total_cmp(&distance_squared(b, point)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{Point3, Vector3};
+
+    use crate::{IntersectionSide, SearchConfig, SiteIndex};
+
+    use super::{classify_site, SiteKind};
+
+    /// A triangle of atoms in the plane `z = 0`, with the centroid `(1.4, 0.808, 0.0)`.
+    fn triangle() -> Vec<Point3<f64>> {
+        vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.8, 0.0, 0.0),
+            Point3::new(1.4, 2.425, 0.0),
+        ]
+    }
+
+    fn classify(
+        points: &[Point3<f64>],
+        site: Point3<f64>,
+        atom_ids: &[usize],
+        side: IntersectionSide,
+    ) -> SiteKind {
+        let to_check: Vec<(usize, Point3<f64>)> =
+            points.iter().enumerate().map(|(i, p)| (i, *p)).collect();
+        let search_config = SearchConfig::new(&to_check, 2.1).with_intersection_side(side);
+        let site_index = SiteIndex::new(points);
+        classify_site(&site, atom_ids, &site_index, &search_config)
+    }
+
+    #[test]
+    fn top_bridge_hollow() {
+        let side = IntersectionSide::default();
+        let points = triangle();
+        let top = classify(&points, Point3::new(0.0, 0.0, 2.0), &[0], side);
+        assert_eq!(top, SiteKind::Top);
+        let bridge = classify(&points, Point3::new(1.4, 0.0, 1.5), &[0, 1], side);
+        assert_eq!(bridge, SiteKind::Bridge);
+        let hollow = Point3::new(1.4, 0.808, 1.2);
+        // An atom of the second layer directly below the hollow
+        let hcp = [triangle(), vec![Point3::new(1.4, 0.808, -2.3)]].concat();
+        assert_eq!(
+            classify(&hcp, hollow, &[0, 1, 2], side),
+            SiteKind::HollowHcp
+        );
+        // An atom of the second layer below the other hollow
+        let fcc = [triangle(), vec![Point3::new(0.0, 1.617, -2.3)]].concat();
+        assert_eq!(
+            classify(&fcc, hollow, &[0, 1, 2], side),
+            SiteKind::HollowFcc
+        );
+    }
+
+    #[test]
+    fn subsurface_by_kept_side() {
+        let points = triangle();
+        let below = Point3::new(1.4, 0.808, -1.2);
+        let subsurface = classify(&points, below, &[0, 1, 2], IntersectionSide::default());
+        assert_eq!(subsurface, SiteKind::Subsurface);
+        // The bottom face of a 2D material when both sides are kept
+        let bottom = classify(&points, below, &[0, 1, 2], IntersectionSide::Both);
+        assert_eq!(bottom, SiteKind::HollowFcc);
+        let down = IntersectionSide::Normal(-Vector3::z_axis());
+        assert_eq!(
+            classify(&points, below, &[0, 1, 2], down),
+            SiteKind::HollowFcc
+        );
+    }
+}
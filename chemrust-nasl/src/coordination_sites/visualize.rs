@@ -2,7 +2,7 @@ use std::f64::consts::FRAC_PI_2;
 
 use castep_periodic_table::element::ElementSymbol;
 use chemrust_core::data::geom::coordinates::CoordData;
-use nalgebra::{Matrix3, Point3, Vector3};
+use nalgebra::{Matrix3, Point3, UnitVector3, Vector3};
 
 use crate::DelegatePoint;
 
@@ -11,6 +11,11 @@ use super::{CoordCircle, CoordSphere, MultiCoordPoint};
 pub trait Visualize {
     type Output;
     fn determine_coord(&self) -> Point3<f64>;
+    /// The coordinate with `normal` as the "up" direction of the model.
+    /// Only differs from `determine_coord` for sites without a fixed point.
+    fn determine_coord_along(&self, _normal: &UnitVector3<f64>) -> Point3<f64> {
+        self.determine_coord()
+    }
     fn element_by_cn_number(&self) -> ElementSymbol;
     fn draw_with_element(&self, element_symbol: ElementSymbol) -> Self::Output;
     fn fractional_coord(&self, cell_tensor: Matrix3<f64>) -> Point3<f64> {
@@ -47,9 +52,11 @@ impl Visualize for CoordSphere {
     }
 
     fn determine_coord(&self) -> Point3<f64> {
-        let center = self.sphere.center();
-        let z_shift = Vector3::z_axis().scale(self.sphere.radius());
-        center + z_shift
+        self.determine_coord_along(&Vector3::z_axis())
+    }
+
+    fn determine_coord_along(&self, normal: &UnitVector3<f64>) -> Point3<f64> {
+        self.sphere.center() + normal.scale(self.sphere.radius())
     }
 
     fn element_by_cn_number(&self) -> ElementSymbol {
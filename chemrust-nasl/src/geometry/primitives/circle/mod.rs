@@ -41,6 +41,26 @@ impl Circle3d {
         (min_dist, max_dist)
     }
 
+    /// The points on the circle nearest to and farthest from the given point.
+    /// `None` if the point is on the axis of the circle, where every point
+    /// on the circle is at the same distance.
+    pub fn nearest_and_farthest_points(
+        &self,
+        point: Point3<f64>,
+    ) -> Option<(Point3<f64>, Point3<f64>)> {
+        let op = point - self.center;
+        let projection = op - self.n.scale(self.n.dot(&op));
+        if projection.norm_squared() < f64::EPSILON {
+            None
+        } else {
+            let direction = UnitVector3::new_normalize(projection);
+            Some((
+                self.center + direction.scale(self.radius),
+                self.center - direction.scale(self.radius),
+            ))
+        }
+    }
+
     pub fn get_point_on_circle(&self, theta: f64) -> Point3<f64> {
         let (x, y, _z) = (self.n().x, self.n().y, self.n().z);
         // We want the v2 to act as the "z-axis" after transformation
@@ -1,7 +1,12 @@
+mod adsorbate;
 mod algorithm;
 mod coordination_sites;
 mod geometry;
 
-pub use algorithm::{search_sites, SearchConfig, SearchReports, SiteIndex};
+pub use adsorbate::{Adsorbate, AdsorbatePose};
+pub use algorithm::{
+    search_sites, IntersectionSide, PeriodicImages, Periodicity, SearchConfig, SearchReports,
+    SiteIndex, SlabAnalysis, SymmetryAnalysis, SymmetryOperation,
+};
 pub use coordination_sites::*;
 pub use geometry::*;